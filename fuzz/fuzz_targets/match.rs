@@ -0,0 +1,36 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Feeds arbitrary robots.txt bodies, user-agents and URLs through the
+//! matcher, which must never panic.
+//!
+//! Run with `cargo fuzz run match` from the root of the repository.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use robotstxt::DefaultMatcher;
+
+fuzz_target!(|input: (&str, &str, &str)| {
+    let (robots_body, user_agent, url) = input;
+    let mut matcher = DefaultMatcher::default();
+    matcher.one_agent_allowed_by_robots(robots_body, user_agent, url);
+
+    let mut matcher = DefaultMatcher::default()
+        .with_unreserved_decoding(true)
+        .with_slash_collapsing(true)
+        .with_whole_segment_matching(true);
+    matcher.one_agent_allowed_by_robots(robots_body, user_agent, url);
+});
@@ -0,0 +1,29 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Feeds arbitrary bytes through the parser, which must never panic.
+//!
+//! Run with `cargo fuzz run parse` from the root of the repository.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use robotstxt::collector::GroupCollector;
+use robotstxt::parse_robotstxt_bytes;
+
+fuzz_target!(|data: &[u8]| {
+    let mut collector = GroupCollector::default();
+    parse_robotstxt_bytes(data, &mut collector);
+});
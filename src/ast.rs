@@ -0,0 +1,359 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::fmt;
+
+use crate::{parse_robotstxt, RobotsParseHandler};
+
+/// A line of a robots.txt other than a user-agent. Values are kept as written,
+/// without %-escaping.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Directive {
+    Allow(String),
+    Disallow(String),
+    /// A sitemap, which applies to the whole robots.txt wherever it appears,
+    /// but still ends the user-agent lines of a group.
+    Sitemap(String),
+    /// Any other directive, such as `Crawl-delay`, with its key as written.
+    Other {
+        key: String,
+        value: String,
+    },
+    /// A comment, without its leading '#' and surrounding whitespace.
+    Comment(String),
+}
+
+/// A user-agent group: one or more consecutive user-agent lines followed by
+/// the directives and comments up to the next group, in robots.txt order.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AstGroup {
+    pub user_agents: Vec<String>,
+    pub directives: Vec<Directive>,
+}
+
+/// An owned representation of a robots.txt, which can be edited and written
+/// back as text with `to_string()`, see [parse_to_ast].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RobotsTxtAst {
+    /// The directives and comments before the first group.
+    pub preamble: Vec<Directive>,
+    pub groups: Vec<AstGroup>,
+}
+
+impl RobotsTxtAst {
+    /// Returns the sitemaps of the preamble and of all groups, in robots.txt
+    /// order.
+    pub fn sitemaps(&self) -> Vec<&str> {
+        self.preamble
+            .iter()
+            .chain(self.groups.iter().flat_map(|group| &group.directives))
+            .filter_map(|directive| match directive {
+                Directive::Sitemap(sitemap) => Some(sitemap.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses a robots.txt into a [RobotsTxtAst].
+///
+/// Writing the AST back gives one line per directive, with a blank line between
+/// groups. Directives keep their place, sitemaps included, so the text written
+/// back has the same groups and gives the same verdicts. Comments are kept in
+/// order, but a comment at the end of a directive's line moves to a line of its
+/// own, and comments between the user-agent lines of a group move after them.
+/// Lines which aren't directives are dropped. Parsing the text written back
+/// gives the same AST.
+/// ```rust
+/// use robotstxt::ast::{parse_to_ast, Directive};
+///
+/// let robots_body = "# Robots\n\
+///                    \n\
+///                    User-agent: FooBot\n\
+///                    Disallow: /private # Keep out\n\
+///                    Sitemap: https://foo.com/sitemap.xml\n";
+/// let mut ast = parse_to_ast(robots_body);
+/// assert_eq!(vec![Directive::Comment("Robots".into())], ast.preamble);
+/// assert_eq!(vec!["https://foo.com/sitemap.xml"], ast.sitemaps());
+/// ast.groups[0]
+///     .directives
+///     .push(Directive::Allow("/private/public".into()));
+/// assert_eq!(
+///     "# Robots\n\
+///      \n\
+///      User-agent: FooBot\n\
+///      Disallow: /private\n\
+///      ## Keep out\n\
+///      Sitemap: https://foo.com/sitemap.xml\n\
+///      Allow: /private/public\n",
+///     ast.to_string()
+/// );
+/// ```
+pub fn parse_to_ast(robots_body: &str) -> RobotsTxtAst {
+    let mut builder = AstBuilder::default();
+    parse_robotstxt(robots_body, &mut builder);
+    builder.ast
+}
+
+/// A [RobotsParseHandler] building a [RobotsTxtAst].
+#[derive(Default)]
+struct AstBuilder {
+    ast: RobotsTxtAst,
+    /// True if a directive other than a user-agent was seen since the last
+    /// user-agent, so that the next user-agent starts a new group.
+    seen_separator: bool,
+    /// The value of the next allow or disallow rule as written.
+    raw_value: Option<String>,
+}
+
+impl AstBuilder {
+    fn push(&mut self, directive: Directive) {
+        match self.ast.groups.last_mut() {
+            Some(group) => group.directives.push(directive),
+            None => self.ast.preamble.push(directive),
+        }
+    }
+
+    fn take_raw_value(&mut self, value: &str) -> String {
+        self.raw_value.take().unwrap_or_else(|| value.to_string())
+    }
+}
+
+impl RobotsParseHandler for AstBuilder {
+    fn handle_robots_start(&mut self) {
+        self.ast = RobotsTxtAst::default();
+        self.seen_separator = false;
+        self.raw_value = None;
+    }
+
+    fn handle_robots_end(&mut self) {}
+
+    fn handle_user_agent(&mut self, _line_num: u32, user_agent: &str) {
+        if self.seen_separator || self.ast.groups.is_empty() {
+            self.ast.groups.push(AstGroup::default());
+            self.seen_separator = false;
+        }
+        if let Some(group) = self.ast.groups.last_mut() {
+            group.user_agents.push(user_agent.to_string());
+        }
+    }
+
+    fn handle_allow(&mut self, _line_num: u32, value: &str) {
+        self.seen_separator = true;
+        let value = self.take_raw_value(value);
+        self.push(Directive::Allow(value));
+    }
+
+    fn handle_disallow(&mut self, _line_num: u32, value: &str) {
+        self.seen_separator = true;
+        let value = self.take_raw_value(value);
+        self.push(Directive::Disallow(value));
+    }
+
+    fn handle_sitemap(&mut self, _line_num: u32, value: &str) {
+        self.seen_separator = true;
+        self.push(Directive::Sitemap(value.to_string()));
+    }
+
+    fn handle_unknown_action(&mut self, _line_num: u32, action: &str, value: &str) {
+        self.seen_separator = true;
+        self.push(Directive::Other {
+            key: action.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    fn handle_comment(&mut self, _line_num: u32, comment: &str) {
+        self.push(Directive::Comment(comment.to_string()));
+    }
+
+    fn handle_raw_value(&mut self, _line_num: u32, raw_value: &str, _escaped_value: &str) {
+        self.raw_value = Some(raw_value.to_string());
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (key, value) = match self {
+            Directive::Allow(value) => ("Allow", value),
+            Directive::Disallow(value) => ("Disallow", value),
+            Directive::Sitemap(value) => ("Sitemap", value),
+            Directive::Other { key, value } => (key.as_str(), value),
+            Directive::Comment(comment) if comment.is_empty() => return write!(f, "#"),
+            Directive::Comment(comment) => return write!(f, "# {}", comment),
+        };
+        if value.is_empty() {
+            write!(f, "{}:", key)
+        } else {
+            write!(f, "{}: {}", key, value)
+        }
+    }
+}
+
+impl fmt::Display for RobotsTxtAst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut blocks = Vec::with_capacity(self.groups.len() + 1);
+        if !self.preamble.is_empty() {
+            let mut block = String::new();
+            for directive in &self.preamble {
+                block.push_str(&format!("{}\n", directive));
+            }
+            blocks.push(block);
+        }
+        for group in &self.groups {
+            let mut block = String::new();
+            for user_agent in &group.user_agents {
+                block.push_str(&format!("User-agent: {}\n", user_agent));
+            }
+            for directive in &group.directives {
+                block.push_str(&format!("{}\n", directive));
+            }
+            blocks.push(block);
+        }
+        write!(f, "{}", blocks.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::*;
+
+    #[test]
+    fn test_parse_to_ast() {
+        let robotstxt = "# Preamble\n\
+        disallow: /outside\n\
+        user-agent: FooBot # First\n\
+        # Between\n\
+        user-agent: BarBot\n\
+        disallow: /café\n\
+        allow:\n\
+        sitemap: https://foo.com/a.xml\n\
+        crawl-delay: 10\n\
+        #\n\
+        user-agent: *\n\
+        \n\
+        user-agent: BazBot\n\
+        disalow: /typo\n\
+        Sitemap: https://foo.com/b.xml\n\
+        not a directive\n";
+        let ast = parse_to_ast(robotstxt);
+        assert_eq!(
+            RobotsTxtAst {
+                preamble: vec![
+                    Directive::Comment("Preamble".into()),
+                    Directive::Disallow("/outside".into()),
+                ],
+                groups: vec![
+                    AstGroup {
+                        user_agents: vec!["FooBot".into(), "BarBot".into()],
+                        directives: vec![
+                            Directive::Comment("First".into()),
+                            Directive::Comment("Between".into()),
+                            Directive::Disallow("/café".into()),
+                            Directive::Allow("".into()),
+                            Directive::Sitemap("https://foo.com/a.xml".into()),
+                            Directive::Other {
+                                key: "crawl-delay".into(),
+                                value: "10".into(),
+                            },
+                            Directive::Comment("".into()),
+                        ],
+                    },
+                    AstGroup {
+                        user_agents: vec!["*".into(), "BazBot".into()],
+                        directives: vec![
+                            Directive::Disallow("/typo".into()),
+                            Directive::Sitemap("https://foo.com/b.xml".into()),
+                        ],
+                    },
+                ],
+            },
+            ast
+        );
+        assert_eq!(
+            vec!["https://foo.com/a.xml", "https://foo.com/b.xml"],
+            ast.sitemaps()
+        );
+
+        let written = "# Preamble\n\
+        Disallow: /outside\n\
+        \n\
+        User-agent: FooBot\n\
+        User-agent: BarBot\n\
+        # First\n\
+        # Between\n\
+        Disallow: /café\n\
+        Allow:\n\
+        Sitemap: https://foo.com/a.xml\n\
+        crawl-delay: 10\n\
+        #\n\
+        \n\
+        User-agent: *\n\
+        User-agent: BazBot\n\
+        Disallow: /typo\n\
+        Sitemap: https://foo.com/b.xml\n";
+        assert_eq!(written, ast.to_string());
+        assert_eq!(ast, parse_to_ast(written));
+        assert_eq!(written, parse_to_ast(written).to_string());
+
+        assert_eq!(RobotsTxtAst::default(), parse_to_ast(""));
+        assert_eq!("", parse_to_ast("").to_string());
+    }
+
+    #[test]
+    fn test_round_trip_keeps_verdicts() {
+        use crate::DefaultMatcher;
+
+        let bodies = [
+            "user-agent: a\n\
+            sitemap: https://x/s.xml\n\
+            user-agent: b\n\
+            disallow: /\n",
+            "sitemap: https://x/s.xml\n\
+            user-agent: a\n\
+            crawl-delay: 1\n\
+            user-agent: b\n\
+            disallow: /x\n\
+            # comment\n\
+            user-agent: *\n\
+            allow: /x/y\n\
+            disallow: /\n\
+            sitemap: https://x/t.xml\n\
+            user-agent: a\n\
+            disallow: /a # comment\n",
+            "disallow: /outside\n\
+            user-agent: a # a\n\
+            # between\n\
+            user-agent: b\n\
+            disallow: /café\n\
+            allow: /café/%41\n\
+            user-agent: * other\n\
+            disalow: /typo\n",
+        ];
+        for body in &bodies {
+            let ast = parse_to_ast(body);
+            let written = ast.to_string();
+            assert_eq!(ast, parse_to_ast(&written), "{}", written);
+            for agent in &["a", "b", "c"] {
+                for path in &["/", "/x", "/x/y", "/a", "/outside", "/caf%C3%A9/A", "/typo"] {
+                    let mut matcher = DefaultMatcher::default();
+                    let before = matcher.one_agent_allowed_by_robots(body, agent, path);
+                    let after = matcher.one_agent_allowed_by_robots(&written, agent, path);
+                    assert_eq!(before, after, "{} {}\n{}", agent, path, written);
+                }
+            }
+        }
+    }
+}
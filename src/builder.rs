@@ -0,0 +1,389 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::time::Duration;
+
+use crate::collector::RuleKind;
+
+#[derive(Debug, Clone, PartialEq)]
+struct BuilderGroup {
+    user_agent: String,
+    rules: Vec<(RuleKind, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+/// A builder to generate robots.txt content.
+///
+/// Every user-agent gets its own group, emitted in the order the user-agent was
+/// first mentioned. Sitemaps are emitted after all groups.
+///
+/// A value can't span lines or hold a `#`, which starts a comment, so these
+/// characters are %-escaped in patterns and sitemap URLs, and removed from
+/// user-agents. A value can thus never add directives of its own:
+/// ```rust
+/// use robotstxt::builder::RobotsTxtBuilder;
+///
+/// let robotstxt = RobotsTxtBuilder::new()
+///     .disallow("FooBot\nDisallow: /", "/a#b\n")
+///     .build();
+/// assert_eq!("User-agent: FooBotDisallow: /\nDisallow: /a%23b%0A\n", robotstxt);
+/// ```
+///
+/// ```rust
+/// use robotstxt::builder::RobotsTxtBuilder;
+///
+/// let robotstxt = RobotsTxtBuilder::new()
+///     .disallow("FooBot", "/private")
+///     .allow("FooBot", "/private/public")
+///     .disallow("*", "/")
+///     .sitemap("https://foo.com/sitemap.xml")
+///     .build();
+/// assert_eq!(
+///     "User-agent: FooBot\n\
+///      Disallow: /private\n\
+///      Allow: /private/public\n\
+///      \n\
+///      User-agent: *\n\
+///      Disallow: /\n\
+///      \n\
+///      Sitemap: https://foo.com/sitemap.xml\n",
+///     robotstxt
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsTxtBuilder {
+    groups: Vec<BuilderGroup>,
+    sitemaps: Vec<String>,
+    sorted: bool,
+}
+
+impl RobotsTxtBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a group for `user_agent`. A group without any rule is emitted
+    /// with an empty `Disallow:`, which allows everything for that user-agent.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.group_mut(user_agent);
+        self
+    }
+
+    /// Add an `Allow` rule to the group of `user_agent`.
+    pub fn allow(self, user_agent: &str, pattern: &str) -> Self {
+        self.rule(user_agent, RuleKind::Allow, pattern)
+    }
+
+    /// Add a `Disallow` rule to the group of `user_agent`.
+    pub fn disallow(self, user_agent: &str, pattern: &str) -> Self {
+        self.rule(user_agent, RuleKind::Disallow, pattern)
+    }
+
+    /// Set the `Crawl-delay` of the group of `user_agent`. It is emitted in
+    /// seconds after the rules of the group. Google ignores this directive.
+    pub fn crawl_delay(mut self, user_agent: &str, delay: Duration) -> Self {
+        self.group_mut(user_agent).crawl_delay = Some(delay);
+        self
+    }
+
+    /// Add a `Sitemap` line.
+    pub fn sitemap(mut self, url: &str) -> Self {
+        self.sitemaps.push(escape_line_breaks(url));
+        self
+    }
+
+    /// Emit the content in a stable order instead of the order it was added in,
+    /// so that the same groups, rules and sitemaps always build the same
+    /// robots.txt, whatever the order they were added in. Groups are sorted by
+    /// user-agent ignoring case, with `*` last, rules by kind (`Allow` first)
+    /// then pattern, and sitemaps by URL. The order of rules doesn't change
+    /// what they match, as the longest match wins.
+    /// ```rust
+    /// use robotstxt::builder::RobotsTxtBuilder;
+    ///
+    /// let robotstxt = RobotsTxtBuilder::new()
+    ///     .disallow("*", "/tmp")
+    ///     .disallow("FooBot", "/private")
+    ///     .allow("FooBot", "/private/public")
+    ///     .disallow("BarBot", "/")
+    ///     .sorted()
+    ///     .build();
+    /// assert_eq!(
+    ///     "User-agent: BarBot\n\
+    ///      Disallow: /\n\
+    ///      \n\
+    ///      User-agent: FooBot\n\
+    ///      Allow: /private/public\n\
+    ///      Disallow: /private\n\
+    ///      \n\
+    ///      User-agent: *\n\
+    ///      Disallow: /tmp\n",
+    ///     robotstxt
+    /// );
+    /// ```
+    pub fn sorted(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+
+    /// Build the robots.txt content.
+    pub fn build(&self) -> String {
+        let mut groups = self.groups.clone();
+        let mut sitemaps = self.sitemaps.clone();
+        if self.sorted {
+            groups.sort_by(|a, b| {
+                (a.user_agent == "*")
+                    .cmp(&(b.user_agent == "*"))
+                    .then_with(|| {
+                        a.user_agent
+                            .to_ascii_lowercase()
+                            .cmp(&b.user_agent.to_ascii_lowercase())
+                    })
+                    .then_with(|| a.user_agent.cmp(&b.user_agent))
+            });
+            for group in &mut groups {
+                group.rules.sort();
+            }
+            sitemaps.sort();
+        }
+
+        let mut blocks = Vec::with_capacity(groups.len() + 1);
+        for group in &groups {
+            let mut block = format!("User-agent: {}\n", group.user_agent);
+            if group.rules.is_empty() {
+                block.push_str("Disallow:\n");
+            }
+            for (kind, pattern) in &group.rules {
+                let key = match kind {
+                    RuleKind::Allow => "Allow",
+                    RuleKind::Disallow => "Disallow",
+                };
+                if pattern.is_empty() {
+                    block.push_str(&format!("{}:\n", key));
+                } else {
+                    block.push_str(&format!("{}: {}\n", key, pattern));
+                }
+            }
+            if let Some(crawl_delay) = group.crawl_delay {
+                block.push_str(&format!("Crawl-delay: {}\n", crawl_delay.as_secs_f64()));
+            }
+            blocks.push(block);
+        }
+        if !sitemaps.is_empty() {
+            let mut block = String::new();
+            for sitemap in &sitemaps {
+                block.push_str(&format!("Sitemap: {}\n", sitemap));
+            }
+            blocks.push(block);
+        }
+        blocks.join("\n")
+    }
+
+    fn rule(mut self, user_agent: &str, kind: RuleKind, pattern: &str) -> Self {
+        self.group_mut(user_agent)
+            .rules
+            .push((kind, escape_line_breaks(pattern)));
+        self
+    }
+
+    fn group_mut(&mut self, user_agent: &str) -> &mut BuilderGroup {
+        let user_agent = &user_agent.replace(['\r', '\n', '#'], "");
+        let index = match self
+            .groups
+            .iter()
+            .position(|group| group.user_agent.eq_ignore_ascii_case(user_agent))
+        {
+            Some(index) => index,
+            None => {
+                self.groups.push(BuilderGroup {
+                    user_agent: user_agent.to_string(),
+                    rules: vec![],
+                    crawl_delay: None,
+                });
+                self.groups.len() - 1
+            }
+        };
+        &mut self.groups[index]
+    }
+}
+
+/// %-escapes the line breaks and `#` of a value, which would otherwise end it.
+fn escape_line_breaks(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            '#' => escaped.push_str("%23"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::*;
+    use crate::{parse_robotstxt, DefaultMatcher, RobotsParseHandler};
+
+    #[test]
+    fn test_build_round_trip() {
+        let robotstxt = RobotsTxtBuilder::new()
+            .user_agent("BarBot")
+            .disallow("FooBot", "/")
+            .allow("foobot", "/public")
+            .build();
+        assert_eq!(
+            "User-agent: BarBot\n\
+             Disallow:\n\
+             \n\
+             User-agent: FooBot\n\
+             Disallow: /\n\
+             Allow: /public\n",
+            robotstxt
+        );
+
+        let mut matcher = DefaultMatcher::default();
+        assert!(matcher.one_agent_allowed_by_robots(&robotstxt, "BarBot", "http://foo.com/"));
+        assert!(!matcher.one_agent_allowed_by_robots(&robotstxt, "FooBot", "http://foo.com/"));
+        assert!(matcher.one_agent_allowed_by_robots(&robotstxt, "FooBot", "http://foo.com/public"));
+    }
+
+    #[test]
+    fn test_build_empty() {
+        assert_eq!("", RobotsTxtBuilder::new().build());
+        assert_eq!(
+            "Sitemap: http://foo.com/sitemap.xml\n",
+            RobotsTxtBuilder::new()
+                .sitemap("http://foo.com/sitemap.xml")
+                .build()
+        );
+    }
+
+    #[test]
+    fn test_build_escapes_values() {
+        let robotstxt = RobotsTxtBuilder::new()
+            .user_agent("FooBot\r\nDisallow: /")
+            .allow("BarBot # all", "/a\nDisallow: /")
+            .sitemap("https://foo.com/s.xml\rUser-agent: *\nDisallow: /")
+            .build();
+        assert_eq!(
+            "User-agent: FooBotDisallow: /\n\
+             Disallow:\n\
+             \n\
+             User-agent: BarBot  all\n\
+             Allow: /a%0ADisallow: /\n\
+             \n\
+             Sitemap: https://foo.com/s.xml%0DUser-agent: *%0ADisallow: /\n",
+            robotstxt
+        );
+
+        let mut matcher = DefaultMatcher::default();
+        for agent in &["FooBot", "BarBot", "BazBot"] {
+            assert!(matcher.one_agent_allowed_by_robots(&robotstxt, agent, "http://foo.com/"));
+        }
+    }
+
+    #[test]
+    fn test_build_crawl_delay() {
+        #[derive(Default)]
+        struct CrawlDelays {
+            user_agent: String,
+            crawl_delays: Vec<(String, f64)>,
+        }
+
+        impl RobotsParseHandler for CrawlDelays {
+            fn handle_robots_start(&mut self) {}
+            fn handle_robots_end(&mut self) {}
+            fn handle_user_agent(&mut self, _line_num: u32, user_agent: &str) {
+                self.user_agent = user_agent.to_string();
+            }
+            fn handle_allow(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_disallow(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_sitemap(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_unknown_action(&mut self, _line_num: u32, action: &str, value: &str) {
+                if action.eq_ignore_ascii_case("crawl-delay") {
+                    let crawl_delay = value.parse().unwrap();
+                    self.crawl_delays
+                        .push((self.user_agent.clone(), crawl_delay));
+                }
+            }
+        }
+
+        let robotstxt = RobotsTxtBuilder::new()
+            .crawl_delay("FooBot", Duration::from_secs(10))
+            .disallow("FooBot", "/private")
+            .crawl_delay("BarBot", Duration::from_millis(500))
+            .build();
+        assert_eq!(
+            "User-agent: FooBot\n\
+             Disallow: /private\n\
+             Crawl-delay: 10\n\
+             \n\
+             User-agent: BarBot\n\
+             Disallow:\n\
+             Crawl-delay: 0.5\n",
+            robotstxt
+        );
+
+        let mut crawl_delays = CrawlDelays::default();
+        parse_robotstxt(&robotstxt, &mut crawl_delays);
+        assert_eq!(
+            vec![("FooBot".to_string(), 10.0), ("BarBot".to_string(), 0.5)],
+            crawl_delays.crawl_delays
+        );
+    }
+
+    #[test]
+    fn test_build_sorted() {
+        let a = RobotsTxtBuilder::new()
+            .disallow("*", "/b")
+            .disallow("*", "/a")
+            .allow("foobot", "/x")
+            .disallow("FooBot", "/y")
+            .crawl_delay("barbot", Duration::from_secs(1))
+            .sitemap("https://foo.com/b.xml")
+            .sitemap("https://foo.com/a.xml");
+        let b = RobotsTxtBuilder::new()
+            .sitemap("https://foo.com/a.xml")
+            .crawl_delay("barbot", Duration::from_secs(1))
+            .disallow("foobot", "/y")
+            .disallow("*", "/a")
+            .sitemap("https://foo.com/b.xml")
+            .allow("FooBot", "/x")
+            .disallow("*", "/b");
+        assert_ne!(a.build(), b.build());
+
+        let sorted = a.sorted().build();
+        assert_eq!(sorted, b.sorted().build());
+        assert_eq!(
+            "User-agent: barbot\n\
+             Disallow:\n\
+             Crawl-delay: 1\n\
+             \n\
+             User-agent: foobot\n\
+             Allow: /x\n\
+             Disallow: /y\n\
+             \n\
+             User-agent: *\n\
+             Disallow: /a\n\
+             Disallow: /b\n\
+             \n\
+             Sitemap: https://foo.com/a.xml\n\
+             Sitemap: https://foo.com/b.xml\n",
+            sorted
+        );
+    }
+}
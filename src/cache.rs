@@ -0,0 +1,347 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::Robots;
+
+/// The number of robots.txt a [RobotsCache] holds by default.
+pub const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+/// The total length of the robots.txt bodies a [RobotsCache] holds by default.
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// A cache of parsed robots.txt, keyed by a hash of their body.
+///
+/// The cache can be shared between threads: lookups lock it only for the time
+/// of the lookup, and the parsed robots.txt is handed out as an [Arc]. Each
+/// entry keeps its body once, to tell apart two robots.txt with the same hash.
+/// The cache is bounded both by its number of entries and by the total length
+/// of their bodies; once full, the least recently used robots.txt are evicted
+/// to make room for a new one.
+///
+/// A [Robots] answers checks for any user-agent, so the user-agents given to
+/// [get_or_parse](Self::get_or_parse) aren't part of the key: a single entry
+/// serves every crawler checking the same robots.txt.
+/// ```rust
+/// use robotstxt::cache::RobotsCache;
+///
+/// let cache = RobotsCache::new();
+/// let robots_body = "user-agent: FooBot\n\
+///                    disallow: /\n";
+/// let foo = cache.get_or_parse(robots_body, &["FooBot"]);
+/// assert_eq!(false, foo.allowed("https://foo.com/"));
+/// let bar = cache.get_or_parse(robots_body, &["BarBot"]);
+/// assert_eq!(true, bar.allowed("https://foo.com/"));
+/// assert_eq!(1, cache.len());
+/// ```
+pub struct RobotsCache {
+    entries: Mutex<Entries>,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+/// A robots.txt from a [RobotsCache] and the user-agents to check it for.
+#[derive(Clone)]
+pub struct CachedRobots {
+    robots: Arc<Robots>,
+    user_agents: Vec<String>,
+}
+
+impl CachedRobots {
+    /// Returns true if 'url' is allowed to be fetched by any of the user-agents,
+    /// see [allowed_by_robots](crate::matcher::RobotsMatcher::allowed_by_robots()).
+    pub fn allowed(&self, url: &str) -> bool {
+        let user_agents: Vec<&str> = self.user_agents.iter().map(String::as_str).collect();
+        self.robots.allowed_by_any(&user_agents, url)
+    }
+
+    /// Returns the parsed robots.txt, shared with the other users of the cache.
+    pub fn robots(&self) -> &Arc<Robots> {
+        &self.robots
+    }
+
+    pub fn user_agents(&self) -> &[String] {
+        &self.user_agents
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    /// The entries by the hash of their body.
+    map: HashMap<u64, Entry>,
+    /// The hashes of the entries by the time they were last used, oldest first.
+    by_last_use: BTreeMap<u64, u64>,
+    /// Incremented on every use of an entry.
+    clock: u64,
+    /// The total length of the bodies of the entries.
+    bytes: usize,
+}
+
+struct Entry {
+    body: Box<str>,
+    robots: Arc<Robots>,
+    last_used: u64,
+}
+
+impl Default for RobotsCache {
+    fn default() -> Self {
+        RobotsCache {
+            entries: Mutex::default(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+impl RobotsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold at most `max_entries` robots.txt, at least one.
+    /// [DEFAULT_MAX_ENTRIES] by default.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Hold robots.txt bodies of at most `max_bytes` in total. A longer body is
+    /// parsed on every lookup, without being cached. [DEFAULT_MAX_BYTES] by
+    /// default.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Returns the parsed form of `robots_body` for `user_agents`, parsing it
+    /// only if no robots.txt with the same body is in the cache yet.
+    pub fn get_or_parse(&self, robots_body: &str, user_agents: &[&str]) -> CachedRobots {
+        CachedRobots {
+            robots: self.robots(robots_body),
+            user_agents: user_agents.iter().map(|agent| agent.to_string()).collect(),
+        }
+    }
+
+    fn robots(&self, robots_body: &str) -> Arc<Robots> {
+        let key = Self::key(robots_body);
+        if let Some(robots) = self.lock().get(key, robots_body) {
+            return robots;
+        }
+        // Parse without holding the lock, other threads may use the cache
+        // meanwhile. If one of them parsed the same body first, its entry wins.
+        let robots = Arc::new(Robots::parse(robots_body));
+        if robots_body.len() > self.max_bytes {
+            return robots;
+        }
+        let mut entries = self.lock();
+        if let Some(robots) = entries.get(key, robots_body) {
+            return robots;
+        }
+        // Another body with the same hash is replaced.
+        entries.remove(key);
+        while entries.map.len() >= self.max_entries
+            || entries.bytes + robots_body.len() > self.max_bytes
+        {
+            entries.evict_least_recently_used();
+        }
+        entries.insert(key, robots_body, Arc::clone(&robots));
+        robots
+    }
+
+    /// Returns the number of robots.txt in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    /// Returns true if the cache holds no robots.txt.
+    pub fn is_empty(&self) -> bool {
+        self.lock().map.is_empty()
+    }
+
+    /// Removes all robots.txt from the cache.
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        entries.map.clear();
+        entries.by_last_use.clear();
+        entries.bytes = 0;
+    }
+
+    fn key(robots_body: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        robots_body.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        // The map is never left half-updated, so a poisoned lock is still usable.
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Entries {
+    fn get(&mut self, key: u64, robots_body: &str) -> Option<Arc<Robots>> {
+        let entry = self.map.get_mut(&key)?;
+        if *entry.body != *robots_body {
+            return None;
+        }
+        self.by_last_use.remove(&entry.last_used);
+        self.clock += 1;
+        entry.last_used = self.clock;
+        self.by_last_use.insert(self.clock, key);
+        Some(Arc::clone(&entry.robots))
+    }
+
+    fn insert(&mut self, key: u64, robots_body: &str, robots: Arc<Robots>) {
+        self.clock += 1;
+        self.by_last_use.insert(self.clock, key);
+        self.bytes += robots_body.len();
+        let entry = Entry {
+            body: robots_body.into(),
+            robots,
+            last_used: self.clock,
+        };
+        self.map.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: u64) {
+        if let Some(entry) = self.map.remove(&key) {
+            self.by_last_use.remove(&entry.last_used);
+            self.bytes -= entry.body.len();
+        }
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        if let Some((_, key)) = self.by_last_use.pop_first() {
+            if let Some(entry) = self.map.remove(&key) {
+                self.bytes -= entry.body.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::*;
+
+    #[test]
+    fn test_cache_is_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RobotsCache>();
+        assert_send_sync::<CachedRobots>();
+
+        let cache = Arc::new(RobotsCache::new());
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    let robots_body = format!("user-agent: *\ndisallow: /{}\n", i % 2);
+                    let robots = cache.get_or_parse(&robots_body, &["FooBot"]);
+                    assert!(!robots.allowed(&format!("http://foo.com/{}", i % 2)));
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(2, cache.len());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_user_agents() {
+        let robots_body = "user-agent: FooBot\n\
+        disallow: /foo\n\
+        user-agent: BarBot\n\
+        disallow: /bar\n";
+        let cache = RobotsCache::new();
+        let foo = cache.get_or_parse(robots_body, &["FooBot"]);
+        let both = cache.get_or_parse(robots_body, &["FooBot", "BarBot"]);
+        assert!(Arc::ptr_eq(foo.robots(), both.robots()));
+        assert_eq!(["FooBot", "BarBot"], both.user_agents());
+        assert!(!foo.allowed("http://foo.com/foo"));
+        assert!(foo.allowed("http://foo.com/bar"));
+        assert!(!both.allowed("http://foo.com/foo"));
+        assert!(!both.allowed("http://foo.com/bar"));
+    }
+
+    #[test]
+    fn test_eviction() {
+        let robots = |cache: &RobotsCache, body: &str| {
+            Arc::clone(cache.get_or_parse(body, &["FooBot"]).robots())
+        };
+        let cache = RobotsCache::new().with_max_entries(2);
+        let a = robots(&cache, "user-agent: *\ndisallow: /a\n");
+        robots(&cache, "user-agent: *\ndisallow: /b\n");
+        // Using 'a' again makes 'b' the least recently used.
+        assert!(Arc::ptr_eq(
+            &a,
+            &robots(&cache, "user-agent: *\ndisallow: /a\n")
+        ));
+        robots(&cache, "user-agent: *\ndisallow: /c\n");
+        assert_eq!(2, cache.len());
+        assert!(Arc::ptr_eq(
+            &a,
+            &robots(&cache, "user-agent: *\ndisallow: /a\n")
+        ));
+
+        let b = robots(&cache, "user-agent: *\ndisallow: /b\n");
+        assert!(!b.allowed("FooBot", "http://foo.com/b"));
+        assert_eq!(2, cache.len());
+
+        let cache = RobotsCache::new().with_max_entries(0);
+        robots(&cache, "");
+        robots(&cache, "user-agent: *\n");
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let a = "user-agent: *\ndisallow: /a\n";
+        let b = "user-agent: *\ndisallow: /b\n";
+        let cache = RobotsCache::new().with_max_bytes(a.len() + b.len());
+        cache.get_or_parse(a, &[]);
+        cache.get_or_parse(b, &[]);
+        assert_eq!(2, cache.len());
+        cache.get_or_parse("user-agent: *\ndisallow: /c\n", &[]);
+        assert_eq!(2, cache.len());
+        // Both are evicted to make room for a longer body.
+        cache.get_or_parse("user-agent: *\ndisallow: /cc\n", &[]);
+        assert_eq!(1, cache.len());
+
+        let cache = RobotsCache::new().with_max_bytes(a.len() - 1);
+        assert!(!cache
+            .get_or_parse(a, &["FooBot"])
+            .allowed("http://foo.com/a"));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_hash_collision() {
+        // Two bodies stored under the same hash are still told apart.
+        let mut entries = Entries::default();
+        let allow_all = Arc::new(Robots::parse("user-agent: *\nallow: /\n"));
+        entries.insert(0, "user-agent: *\nallow: /\n", allow_all);
+        assert!(entries.get(0, "user-agent: *\ndisallow: /\n").is_none());
+        assert!(entries.get(0, "user-agent: *\nallow: /\n").is_some());
+    }
+}
@@ -0,0 +1,639 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use crate::parser::Lines;
+use crate::{parse_robotstxt, DefaultMatcher, RobotsParseHandler};
+
+/// The kind of a path rule in robots.txt.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum RuleKind {
+    Allow,
+    Disallow,
+}
+
+/// An allow or disallow rule, with its (already escaped) pattern and the line
+/// it was found on.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rule {
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub line: u32,
+}
+
+/// A user-agent group: one or more consecutive user-agent lines followed by
+/// the rules that apply to them.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Group {
+    pub user_agents: Vec<String>,
+    pub rules: Vec<Rule>,
+    /// Line of the first user-agent of the group.
+    pub start_line: u32,
+    /// Last line holding a user-agent, rule or unknown directive of the group.
+    pub end_line: u32,
+}
+
+impl Group {
+    /// Returns true if the group names `user_agent` specifically.
+    pub fn is_specific_to(&self, user_agent: &str) -> bool {
+        self.user_agents.iter().any(|agent| {
+            !DefaultMatcher::is_global_agent(agent)
+                && DefaultMatcher::extract_user_agent(agent).eq_ignore_ascii_case(user_agent)
+        })
+    }
+
+    /// Returns true if the group applies to all user-agents.
+    pub fn is_global(&self) -> bool {
+        self.user_agents
+            .iter()
+            .any(|agent| DefaultMatcher::is_global_agent(agent))
+    }
+}
+
+/// A [RobotsParseHandler] which collects the user-agent groups and sitemaps
+/// of a robots.txt.
+///
+/// Group boundaries follow the same rule as the [RobotsMatcher](crate::matcher::RobotsMatcher):
+/// a user-agent line starts a new group once any other directive has been seen
+/// since the previous user-agent line. Rules found before the first user-agent
+/// line don't belong to any group and are ignored.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GroupCollector {
+    groups: Vec<Group>,
+    sitemaps: Vec<String>,
+    seen_separator: bool,
+}
+
+impl GroupCollector {
+    /// Returns the groups in the order they appear in the robots.txt.
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    /// Returns the sitemap values in the order they appear in the robots.txt.
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+
+    /// Returns the groups whose rules `user_agent` obeys: the groups naming it
+    /// specifically if there are any, else the global groups.
+    pub fn governing_groups(&self, user_agent: &str) -> Vec<&Group> {
+        let specific: Vec<&Group> = self
+            .groups
+            .iter()
+            .filter(|group| group.is_specific_to(user_agent))
+            .collect();
+        if !specific.is_empty() {
+            return specific;
+        }
+        self.groups
+            .iter()
+            .filter(|group| group.is_global())
+            .collect()
+    }
+
+    fn add_rule(&mut self, kind: RuleKind, line_num: u32, value: &str) {
+        if let Some(group) = self.groups.last_mut() {
+            self.seen_separator = true;
+            group.end_line = line_num;
+            group.rules.push(Rule {
+                kind,
+                pattern: value.to_string(),
+                line: line_num,
+            });
+        }
+    }
+}
+
+impl RobotsParseHandler for GroupCollector {
+    fn handle_robots_start(&mut self) {
+        self.groups.clear();
+        self.sitemaps.clear();
+        self.seen_separator = false;
+    }
+
+    fn handle_robots_end(&mut self) {}
+
+    fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+        if self.seen_separator || self.groups.is_empty() {
+            self.groups.push(Group {
+                start_line: line_num,
+                ..Default::default()
+            });
+            self.seen_separator = false;
+        }
+        if let Some(group) = self.groups.last_mut() {
+            group.user_agents.push(user_agent.to_string());
+            group.end_line = line_num;
+        }
+    }
+
+    fn handle_allow(&mut self, line_num: u32, value: &str) {
+        self.add_rule(RuleKind::Allow, line_num, value);
+    }
+
+    fn handle_disallow(&mut self, line_num: u32, value: &str) {
+        self.add_rule(RuleKind::Disallow, line_num, value);
+    }
+
+    fn handle_sitemap(&mut self, _line_num: u32, value: &str) {
+        self.seen_separator = true;
+        self.sitemaps.push(value.to_string());
+    }
+
+    fn handle_unknown_action(&mut self, line_num: u32, _action: &str, _value: &str) {
+        self.seen_separator = true;
+        if let Some(group) = self.groups.last_mut() {
+            group.end_line = line_num;
+        }
+    }
+}
+
+/// A [RobotsParseHandler] which collects the sitemaps of a robots.txt, with the
+/// line each of them appears on.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SitemapCollector {
+    sitemaps: Vec<(u32, String)>,
+}
+
+impl SitemapCollector {
+    /// Returns the sitemap values and their line, in robots.txt order.
+    pub fn sitemaps(&self) -> &[(u32, String)] {
+        &self.sitemaps
+    }
+
+    /// Consumes the collector, returning the sitemap values and their line.
+    pub fn into_sitemaps(self) -> Vec<(u32, String)> {
+        self.sitemaps
+    }
+
+    /// Returns the sitemap values in robots.txt order, duplicates included.
+    pub fn urls(&self) -> Vec<String> {
+        self.sitemaps.iter().map(|(_, url)| url.clone()).collect()
+    }
+
+    /// Returns the sitemap values without duplicates, in the order they are
+    /// first seen. Values are compared exactly, so `http://foo.bar/a.xml` and
+    /// `http://FOO.bar/a.xml` are both kept.
+    /// ```rust
+    /// use robotstxt::collector::SitemapCollector;
+    /// use robotstxt::parse_robotstxt;
+    ///
+    /// let mut collector = SitemapCollector::default();
+    /// parse_robotstxt(
+    ///     "sitemap: /b.xml\nsitemap: /a.xml\nsitemap: /b.xml\n",
+    ///     &mut collector,
+    /// );
+    /// assert_eq!(vec!["/b.xml", "/a.xml", "/b.xml"], collector.urls());
+    /// assert_eq!(vec!["/b.xml", "/a.xml"], collector.unique());
+    /// ```
+    pub fn unique(&self) -> Vec<String> {
+        let mut unique: Vec<String> = vec![];
+        for (_, url) in &self.sitemaps {
+            if !unique.contains(url) {
+                unique.push(url.clone());
+            }
+        }
+        unique
+    }
+}
+
+impl RobotsParseHandler for SitemapCollector {
+    fn handle_robots_start(&mut self) {
+        self.sitemaps.clear();
+    }
+
+    fn handle_robots_end(&mut self) {}
+
+    fn handle_user_agent(&mut self, _line_num: u32, _user_agent: &str) {}
+
+    fn handle_allow(&mut self, _line_num: u32, _value: &str) {}
+
+    fn handle_disallow(&mut self, _line_num: u32, _value: &str) {}
+
+    fn handle_sitemap(&mut self, line_num: u32, value: &str) {
+        self.sitemaps.push((line_num, value.to_string()));
+    }
+
+    fn handle_unknown_action(&mut self, _line_num: u32, _action: &str, _value: &str) {}
+}
+
+/// Returns the sitemap values of a robots.txt with the line each of them
+/// appears on, in robots.txt order.
+/// ```rust
+/// use robotstxt::collector::collect_sitemaps_with_lines;
+///
+/// let robots_body = "Sitemap: https://foo.com/a.xml\n\
+///                    User-agent: *\n\
+///                    Disallow: /\n\
+///                    sitemap: https://foo.com/b.xml\n";
+/// assert_eq!(
+///     vec![(1, "https://foo.com/a.xml".to_string()), (4, "https://foo.com/b.xml".to_string())],
+///     collect_sitemaps_with_lines(robots_body)
+/// );
+/// ```
+pub fn collect_sitemaps_with_lines(robots_body: &str) -> Vec<(u32, String)> {
+    let mut collector = SitemapCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+    collector.into_sitemaps()
+}
+
+/// Returns the allow and disallow rules `user_agent` obeys, ordered by
+/// descending pattern length, which is the priority the longest-match strategy
+/// gives them. Among rules of equal length, allow rules come first as they win
+/// ties; otherwise the robots.txt order is kept.
+/// ```rust
+/// use robotstxt::collector::{resolved_rules, RuleKind};
+///
+/// let robots_body = "User-agent: *\n\
+///                    Disallow: /\n\
+///                    Allow: /public\n\
+///                    Disallow: /public/x\n\
+///                    \n\
+///                    User-agent: BarBot\n\
+///                    Disallow: /bar\n";
+/// let rules: Vec<_> = resolved_rules(robots_body, "FooBot")
+///     .into_iter()
+///     .map(|rule| (rule.kind, rule.pattern))
+///     .collect();
+/// assert_eq!(
+///     vec![
+///         (RuleKind::Disallow, "/public/x".to_string()),
+///         (RuleKind::Allow, "/public".to_string()),
+///         (RuleKind::Disallow, "/".to_string()),
+///     ],
+///     rules
+/// );
+/// ```
+pub fn resolved_rules(robots_body: &str, user_agent: &str) -> Vec<Rule> {
+    let mut collector = GroupCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+
+    let mut rules: Vec<Rule> = collector
+        .governing_groups(user_agent)
+        .into_iter()
+        .flat_map(|group| group.rules.iter().cloned())
+        .collect();
+    rules.sort_by(|a, b| {
+        b.pattern
+            .len()
+            .cmp(&a.pattern.len())
+            .then_with(|| a.kind.cmp(&b.kind))
+    });
+    rules
+}
+
+/// Returns the patterns of the allow rules `user_agent` obeys, in robots.txt
+/// order: those of the groups naming it specifically if there are any, else
+/// those of the global groups. Handy to show the carve-outs of a broad
+/// disallow rule.
+/// ```rust
+/// use robotstxt::collector::allow_patterns_for;
+///
+/// let robots_body = "User-agent: *\n\
+///                    Disallow: /\n\
+///                    Allow: /public\n\
+///                    Allow: /about$\n\
+///                    \n\
+///                    User-agent: BarBot\n\
+///                    Allow: /bar\n";
+/// assert_eq!(vec!["/public", "/about$"], allow_patterns_for(robots_body, "FooBot"));
+/// assert_eq!(vec!["/bar"], allow_patterns_for(robots_body, "BarBot"));
+/// ```
+pub fn allow_patterns_for(robots_body: &str, user_agent: &str) -> Vec<String> {
+    patterns_for(robots_body, user_agent, RuleKind::Allow)
+}
+
+/// Same as [allow_patterns_for], for the disallow rules.
+/// ```rust
+/// use robotstxt::collector::disallow_patterns_for;
+///
+/// let robots_body = "User-agent: *\n\
+///                    Disallow: /\n\
+///                    Allow: /public\n";
+/// assert_eq!(vec!["/"], disallow_patterns_for(robots_body, "FooBot"));
+/// ```
+pub fn disallow_patterns_for(robots_body: &str, user_agent: &str) -> Vec<String> {
+    patterns_for(robots_body, user_agent, RuleKind::Disallow)
+}
+
+fn patterns_for(robots_body: &str, user_agent: &str, kind: RuleKind) -> Vec<String> {
+    let mut collector = GroupCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+
+    collector
+        .governing_groups(user_agent)
+        .into_iter()
+        .flat_map(|group| group.rules.iter())
+        .filter(|rule| rule.kind == kind)
+        .map(|rule| rule.pattern.clone())
+        .collect()
+}
+
+/// Returns the distinct user-agents having rules in the robots.txt, in the
+/// order they first appear: those of the groups holding at least one allow or
+/// disallow rule. Each agent is given as its matchable part, e.g. `Googlebot`
+/// for `Googlebot/2.1`, and `*` for the global groups. Agents are compared
+/// ignoring case, the first spelling is kept.
+/// ```rust
+/// use robotstxt::collector::defined_agents;
+///
+/// let robots_body = "User-agent: Googlebot/2.1\n\
+///                    User-agent: Bingbot\n\
+///                    Disallow: /private\n\
+///                    \n\
+///                    User-agent: NoRulesBot\n\
+///                    Crawl-delay: 10\n\
+///                    \n\
+///                    User-agent: *\n\
+///                    User-agent: googlebot\n\
+///                    Allow: /\n";
+/// assert_eq!(vec!["Googlebot", "Bingbot", "*"], defined_agents(robots_body));
+/// ```
+pub fn defined_agents(robots_body: &str) -> Vec<String> {
+    let mut collector = GroupCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+
+    let mut agents: Vec<String> = vec![];
+    for group in collector.groups() {
+        if group.rules.is_empty() {
+            continue;
+        }
+        for user_agent in &group.user_agents {
+            let agent = if DefaultMatcher::is_global_agent(user_agent) {
+                "*"
+            } else {
+                DefaultMatcher::extract_user_agent(user_agent)
+            };
+            if !agent.is_empty() && !agents.iter().any(|seen| seen.eq_ignore_ascii_case(agent)) {
+                agents.push(agent.to_string());
+            }
+        }
+    }
+    agents
+}
+
+/// Returns the number of user-agent groups of the robots.txt. Consecutive
+/// user-agent lines make a single group, and a user-agent line after any other
+/// directive starts a new one. Groups for the same user-agent are counted
+/// separately, even though crawlers merge them.
+/// ```rust
+/// use robotstxt::collector::group_count;
+///
+/// let robots_body = "User-agent: FooBot\n\
+///                    User-agent: BarBot\n\
+///                    Disallow: /private\n\
+///                    \n\
+///                    User-agent: *\n\
+///                    # Comments don't end a group.\n\
+///                    User-agent: BazBot\n\
+///                    Allow: /\n\
+///                    \n\
+///                    User-agent: FooBot\n";
+/// assert_eq!(3, group_count(robots_body));
+/// assert_eq!(0, group_count("Disallow: /\n"));
+/// ```
+pub fn group_count(robots_body: &str) -> usize {
+    let mut collector = GroupCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+    collector.groups().len()
+}
+
+/// Returns the raw text of the groups governing `user_agent`, from the first
+/// user-agent line to the last line of each group, exactly as written in the
+/// robots.txt. Several governing groups are separated by a newline, and an
+/// empty string is returned if no group applies.
+/// ```rust
+/// use robotstxt::collector::governing_group_text;
+///
+/// let robots_body = "User-agent: *\n\
+///                    Disallow: /\n\
+///                    \n\
+///                    User-agent: FooBot # Our bot\n\
+///                    Disallow: /private\n\
+///                    Sitemap: https://foo.com/sitemap.xml\n";
+/// assert_eq!(
+///     "User-agent: FooBot # Our bot\nDisallow: /private",
+///     governing_group_text(robots_body, "FooBot")
+/// );
+/// assert_eq!("User-agent: *\nDisallow: /", governing_group_text(robots_body, "BarBot"));
+/// ```
+pub fn governing_group_text(robots_body: &str, user_agent: &str) -> String {
+    let mut collector = GroupCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+
+    let lines: Vec<&str> = Lines::new(robots_body).map(|(_, line)| line).collect();
+    let offset = |line: &str| line.as_ptr() as usize - robots_body.as_ptr() as usize;
+    collector
+        .governing_groups(user_agent)
+        .iter()
+        .map(|group| {
+            let first = lines[group.start_line as usize - 1];
+            let last = lines[group.end_line as usize - 1];
+            &robots_body[offset(first)..offset(last) + last.len()]
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::*;
+    use crate::parse_robotstxt;
+
+    #[test]
+    fn test_collect_groups() {
+        let robotstxt = "allow: /ignored\n\
+        user-agent: FooBot\n\
+        user-agent: BarBot\n\
+        disallow: /a\n\
+        sitemap: http://foo.bar/sitemap.xml\n\
+        allow: /a/b\n\
+        user-agent: *\n\
+        crawl-delay: 1\n\
+        user-agent: BazBot\n\
+        disallow: /\n";
+        let mut collector = GroupCollector::default();
+        parse_robotstxt(robotstxt, &mut collector);
+
+        let groups = collector.groups();
+        assert_eq!(3, groups.len());
+        assert_eq!(vec!["FooBot", "BarBot"], groups[0].user_agents);
+        assert_eq!(
+            vec![
+                Rule {
+                    kind: RuleKind::Disallow,
+                    pattern: "/a".into(),
+                    line: 4
+                },
+                Rule {
+                    kind: RuleKind::Allow,
+                    pattern: "/a/b".into(),
+                    line: 6
+                }
+            ],
+            groups[0].rules
+        );
+        assert_eq!(vec!["*"], groups[1].user_agents);
+        assert!(groups[1].rules.is_empty());
+        assert_eq!(vec!["BazBot"], groups[2].user_agents);
+        assert_eq!(1, groups[2].rules.len());
+        assert_eq!(vec!["http://foo.bar/sitemap.xml"], collector.sitemaps());
+        assert_eq!((2, 6), (groups[0].start_line, groups[0].end_line));
+        assert_eq!((7, 8), (groups[1].start_line, groups[1].end_line));
+        assert_eq!((9, 10), (groups[2].start_line, groups[2].end_line));
+    }
+
+    #[test]
+    fn test_governing_group_text() {
+        let robotstxt = "\u{FEFF}user-agent: FooBot\r\n\
+        disallow: /a\r\n\
+        \r\n\
+        user-agent: *\r\n\
+        disallow: /\r\n\
+        \r\n\
+        user-agent: foobot/1.0\r\n\
+        allow: /b\r\n";
+        assert_eq!(
+            "user-agent: FooBot\r\ndisallow: /a\nuser-agent: foobot/1.0\r\nallow: /b",
+            governing_group_text(robotstxt, "FooBot")
+        );
+        assert_eq!(
+            "user-agent: *\r\ndisallow: /",
+            governing_group_text(robotstxt, "BarBot")
+        );
+        assert_eq!("", governing_group_text("user-agent: FooBot\n", "BarBot"));
+    }
+
+    #[test]
+    fn test_resolved_rules() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /a\n\
+        allow: /ab\n\
+        disallow: /ab\n\
+        \n\
+        user-agent: *\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        allow: /abc\n";
+        let rules: Vec<_> = resolved_rules(robotstxt, "FooBot")
+            .into_iter()
+            .map(|rule| (rule.kind, rule.pattern, rule.line))
+            .collect();
+        assert_eq!(
+            vec![
+                (RuleKind::Allow, "/abc".to_string(), 10),
+                (RuleKind::Allow, "/ab".to_string(), 3),
+                (RuleKind::Disallow, "/ab".to_string(), 4),
+                (RuleKind::Disallow, "/a".to_string(), 2),
+            ],
+            rules
+        );
+        assert_eq!(1, resolved_rules(robotstxt, "BarBot").len());
+        assert!(resolved_rules("", "FooBot").is_empty());
+    }
+
+    #[test]
+    fn test_patterns_for() {
+        let robotstxt = "allow: /ignored\n\
+        user-agent: FooBot\n\
+        disallow: /\n\
+        allow: /public\n\
+        user-agent: *\n\
+        allow: /global\n\
+        \n\
+        user-agent: foobot/2.1\n\
+        allow: /caf\u{e9}\n\
+        disallow: /private\n";
+        assert_eq!(
+            vec!["/public", "/caf%C3%A9"],
+            allow_patterns_for(robotstxt, "FooBot")
+        );
+        assert_eq!(
+            vec!["/", "/private"],
+            disallow_patterns_for(robotstxt, "FooBot")
+        );
+        assert_eq!(vec!["/global"], allow_patterns_for(robotstxt, "BarBot"));
+        assert!(allow_patterns_for("", "FooBot").is_empty());
+        assert!(disallow_patterns_for("user-agent: FooBot\nallow: /\n", "BarBot").is_empty());
+    }
+
+    #[test]
+    fn test_defined_agents() {
+        let robotstxt = "allow: /ignored\n\
+        user-agent: FooBot\n\
+        \n\
+        user-agent: BarBot\n\
+        sitemap: /sitemap.xml\n\
+        user-agent: BazBot\n\
+        disallow:\n\
+        user-agent: * everyone\n\
+        user-agent: /invalid\n\
+        allow: /a\n\
+        user-agent: FooBot\n";
+        // FooBot's groups, at the start and at the end, are both empty, and
+        // BarBot's group ends at the sitemap line.
+        assert_eq!(vec!["BazBot", "*"], defined_agents(robotstxt));
+        assert!(defined_agents("").is_empty());
+        assert!(defined_agents("user-agent: FooBot\n").is_empty());
+    }
+
+    #[test]
+    fn test_collect_sitemaps_with_lines() {
+        let robotstxt = "\u{FEFF}# Sitemaps\r\n\
+        Sitemap: http://foo.bar/a.xml # main\r\n\
+        \r\n\
+        user-agent: *\r\
+        site-map: /relative.xml\r\
+        sitemap:\n";
+        assert_eq!(
+            vec![
+                (2, "http://foo.bar/a.xml".to_string()),
+                (5, "/relative.xml".to_string()),
+                (6, "".to_string()),
+            ],
+            collect_sitemaps_with_lines(robotstxt)
+        );
+        assert!(collect_sitemaps_with_lines("").is_empty());
+
+        let mut collector = SitemapCollector::default();
+        parse_robotstxt(
+            "sitemap: http://foo.bar/a.xml\n\
+            sitemap: http://foo.bar/b.xml\n\
+            user-agent: *\n\
+            sitemap: http://foo.bar/a.xml\n\
+            sitemap: http://FOO.bar/a.xml\n",
+            &mut collector,
+        );
+        assert_eq!(4, collector.sitemaps().len());
+        assert_eq!(
+            vec![
+                "http://foo.bar/a.xml",
+                "http://foo.bar/b.xml",
+                "http://foo.bar/a.xml",
+                "http://FOO.bar/a.xml",
+            ],
+            collector.urls()
+        );
+        assert_eq!(
+            vec![
+                "http://foo.bar/a.xml",
+                "http://foo.bar/b.xml",
+                "http://FOO.bar/a.xml",
+            ],
+            collector.unique()
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::HashSet;
+
+use crate::collector::{resolved_rules, Rule, RuleKind};
+use crate::DefaultMatcher;
+
+/// A URL whose verdict differs between two robots.txt revisions.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UrlChange {
+    pub url: String,
+    pub old_allowed: bool,
+    pub new_allowed: bool,
+}
+
+/// Checks every URL against the old and the new robots.txt for `user_agent`,
+/// and returns the URLs whose verdict changed, in the order they were given.
+/// URLs must be %-encoded according to RFC3986.
+/// ```rust
+/// use robotstxt::diff::{diff_impact, UrlChange};
+///
+/// let old_body = "user-agent: *\n\
+///                 disallow: /private\n";
+/// let new_body = "user-agent: *\n\
+///                 disallow: /private\n\
+///                 disallow: /tmp\n\
+///                 allow: /private/public\n";
+/// let urls = [
+///     "https://foo.com/",
+///     "https://foo.com/tmp/a",
+///     "https://foo.com/private/public",
+/// ];
+/// assert_eq!(
+///     vec![
+///         UrlChange {
+///             url: "https://foo.com/tmp/a".to_string(),
+///             old_allowed: true,
+///             new_allowed: false,
+///         },
+///         UrlChange {
+///             url: "https://foo.com/private/public".to_string(),
+///             old_allowed: false,
+///             new_allowed: true,
+///         },
+///     ],
+///     diff_impact(old_body, new_body, "FooBot", &urls)
+/// );
+/// ```
+pub fn diff_impact(
+    old_body: &str,
+    new_body: &str,
+    user_agent: &str,
+    urls: &[&str],
+) -> Vec<UrlChange> {
+    let mut old_matcher = DefaultMatcher::default();
+    let mut new_matcher = DefaultMatcher::default();
+    urls.iter()
+        .filter_map(|url| {
+            let old_allowed = old_matcher.one_agent_allowed_by_robots(old_body, user_agent, url);
+            let new_allowed = new_matcher.one_agent_allowed_by_robots(new_body, user_agent, url);
+            if old_allowed == new_allowed {
+                return None;
+            }
+            Some(UrlChange {
+                url: url.to_string(),
+                old_allowed,
+                new_allowed,
+            })
+        })
+        .collect()
+}
+
+/// The allow and disallow rules added, removed and kept between two robots.txt
+/// revisions for a user-agent, see [rule_diff]. Added and unchanged rules carry
+/// their line in the new robots.txt, removed rules their line in the old one.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RuleDiff {
+    pub added: Vec<Rule>,
+    pub removed: Vec<Rule>,
+    pub unchanged: Vec<Rule>,
+}
+
+impl RuleDiff {
+    /// Returns true if no rule was added or removed.
+    pub fn is_unchanged(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compares the rules `user_agent` obeys in the old and the new robots.txt, as
+/// given by [resolved_rules]. Rules are compared by kind and pattern, wherever
+/// they are written: moving a rule to another line or to another group
+/// governing the agent doesn't change it, and a repeated rule counts once. Each
+/// list is in the priority order of [resolved_rules].
+///
+/// Unlike [diff_impact], this tells how the rules were edited, not which URLs
+/// are affected: a new rule may be redundant, and removing a group naming the
+/// agent can replace all of its rules by those of the global groups.
+/// ```rust
+/// use robotstxt::collector::Rule;
+/// use robotstxt::diff::rule_diff;
+///
+/// fn patterns(rules: &[Rule]) -> Vec<&str> {
+///     rules.iter().map(|rule| rule.pattern.as_str()).collect()
+/// }
+///
+/// let old_body = "user-agent: *\n\
+///                 disallow: /private\n\
+///                 disallow: /tmp\n";
+/// let new_body = "user-agent: *\n\
+///                 disallow: /private\n\
+///                 allow: /private/public\n";
+/// let diff = rule_diff(old_body, new_body, "FooBot");
+/// assert_eq!(vec!["/private/public"], patterns(&diff.added));
+/// assert_eq!(vec!["/tmp"], patterns(&diff.removed));
+/// assert_eq!(vec!["/private"], patterns(&diff.unchanged));
+/// ```
+pub fn rule_diff(old_body: &str, new_body: &str, user_agent: &str) -> RuleDiff {
+    let old_rules = distinct_rules(resolved_rules(old_body, user_agent));
+    let new_rules = distinct_rules(resolved_rules(new_body, user_agent));
+    let key = |rule: &Rule| (rule.kind, rule.pattern.clone());
+    let old_keys: HashSet<(RuleKind, String)> = old_rules.iter().map(key).collect();
+    let new_keys: HashSet<(RuleKind, String)> = new_rules.iter().map(key).collect();
+
+    let mut diff = RuleDiff::default();
+    for rule in new_rules {
+        if old_keys.contains(&key(&rule)) {
+            diff.unchanged.push(rule);
+        } else {
+            diff.added.push(rule);
+        }
+    }
+    diff.removed = old_rules
+        .into_iter()
+        .filter(|rule| !new_keys.contains(&key(rule)))
+        .collect();
+    diff
+}
+
+/// Drops the repetitions of a rule, keeping its first occurrence.
+fn distinct_rules(rules: Vec<Rule>) -> Vec<Rule> {
+    let mut seen = HashSet::new();
+    rules
+        .into_iter()
+        .filter(|rule| seen.insert((rule.kind, rule.pattern.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::*;
+
+    #[test]
+    fn test_diff_impact_agent_groups() {
+        let old_body = "user-agent: *\n\
+        disallow: /\n";
+        let new_body = "user-agent: *\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /private\n";
+        let urls = ["http://foo.com/", "http://foo.com/private"];
+        assert!(diff_impact(old_body, new_body, "BarBot", &urls).is_empty());
+        assert_eq!(
+            vec![UrlChange {
+                url: "http://foo.com/".into(),
+                old_allowed: false,
+                new_allowed: true,
+            }],
+            diff_impact(old_body, new_body, "FooBot", &urls)
+        );
+        assert!(diff_impact(new_body, new_body, "FooBot", &urls).is_empty());
+    }
+
+    #[test]
+    fn test_rule_diff() {
+        let old_body = "user-agent: *\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /private\n\
+        allow: /public\n\
+        disallow: /private\n";
+        let new_body = "user-agent: FooBot\n\
+        allow: /public\n\
+        \n\
+        user-agent: *\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /public\n";
+        let rule = |kind, pattern: &str, line| Rule {
+            kind,
+            pattern: pattern.into(),
+            line,
+        };
+        assert_eq!(
+            RuleDiff {
+                added: vec![rule(RuleKind::Disallow, "/public", 8)],
+                removed: vec![rule(RuleKind::Disallow, "/private", 5)],
+                unchanged: vec![rule(RuleKind::Allow, "/public", 2)],
+            },
+            rule_diff(old_body, new_body, "FooBot")
+        );
+
+        // Without a group of its own, BarBot obeys the unchanged global group.
+        let diff = rule_diff(old_body, new_body, "BarBot");
+        assert!(diff.is_unchanged());
+        assert_eq!(vec![rule(RuleKind::Disallow, "/", 5)], diff.unchanged);
+
+        // Removing the groups naming FooBot falls back to the global ones.
+        let diff = rule_diff(old_body, "user-agent: *\ndisallow: /\n", "FooBot");
+        assert_eq!(vec![rule(RuleKind::Disallow, "/", 2)], diff.added);
+        assert_eq!(2, diff.removed.len());
+        assert!(diff.unchanged.is_empty());
+
+        assert!(rule_diff(old_body, old_body, "FooBot").is_unchanged());
+        assert_eq!(RuleDiff::default(), rule_diff("", "", "FooBot"));
+    }
+}
@@ -0,0 +1,438 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::collector::{Group, GroupCollector, Rule, RuleKind};
+use crate::{parse_robotstxt, RobotsParseHandler};
+
+/// A questionable construct found in a robots.txt by [lint].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LintIssue {
+    /// The same rule appears twice in a group. The second one has no effect.
+    DuplicateRule {
+        kind: RuleKind,
+        pattern: String,
+        first_line: u32,
+        line: u32,
+    },
+    /// A group both allows and disallows the same pattern. The allow rule wins.
+    ConflictingRules {
+        pattern: String,
+        allow_line: u32,
+        disallow_line: u32,
+    },
+    /// Two rules of a group are written differently but have the same pattern
+    /// once %-escaped, e.g. `/café` and `/caf%C3%A9`. They match the same
+    /// paths, which is likely not obvious to whoever wrote them.
+    EquivalentEncodings {
+        pattern: String,
+        first_source: String,
+        first_line: u32,
+        source: String,
+        line: u32,
+    },
+    /// A meta robots directive, such as `noarchive`, which crawlers only honor
+    /// in a `<meta name="robots">` tag or an `X-Robots-Tag` header, not in a
+    /// robots.txt. `directive` is lowercased.
+    MetaRobotsDirective { directive: String, line: u32 },
+}
+
+/// Directives of the meta robots tag and the `X-Robots-Tag` header, which are
+/// sometimes misplaced in a robots.txt.
+const META_ROBOTS_DIRECTIVES: &[&str] = &[
+    "noindex",
+    "nofollow",
+    "noarchive",
+    "nocache",
+    "nosnippet",
+    "noimageindex",
+    "notranslate",
+    "indexifembedded",
+    "max-snippet",
+    "max-image-preview",
+    "max-video-preview",
+    "unavailable_after",
+];
+
+impl LintIssue {
+    /// Returns the line the issue is found on, the later one for issues
+    /// involving two lines.
+    pub fn line(&self) -> u32 {
+        match self {
+            LintIssue::DuplicateRule { line, .. }
+            | LintIssue::EquivalentEncodings { line, .. }
+            | LintIssue::MetaRobotsDirective { line, .. } => *line,
+            LintIssue::ConflictingRules {
+                allow_line,
+                disallow_line,
+                ..
+            } => *allow_line.max(disallow_line),
+        }
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::DuplicateRule {
+                pattern,
+                first_line,
+                line,
+                ..
+            } => write!(
+                f,
+                "line {}: rule for '{}' duplicates line {}",
+                line, pattern, first_line
+            ),
+            LintIssue::ConflictingRules {
+                pattern,
+                allow_line,
+                disallow_line,
+            } => write!(
+                f,
+                "line {}: '{}' is both allowed (line {}) and disallowed (line {})",
+                self.line(),
+                pattern,
+                allow_line,
+                disallow_line
+            ),
+            LintIssue::EquivalentEncodings {
+                source,
+                first_source,
+                first_line,
+                line,
+                ..
+            } => write!(
+                f,
+                "line {}: '{}' is the same pattern as '{}' on line {}",
+                line, source, first_source, first_line
+            ),
+            LintIssue::MetaRobotsDirective { directive, line } => write!(
+                f,
+                "line {}: '{}' is probably meant for a meta robots tag, robots.txt doesn't support it",
+                line, directive
+            ),
+        }
+    }
+}
+
+/// Checks a robots.txt for questionable constructs, reported in the order of
+/// the line they are found on.
+/// ```rust
+/// use robotstxt::lint::{lint, LintIssue};
+///
+/// let robots_body = "User-agent: FooBot\n\
+///                    Disallow: /private\n\
+///                    Allow: /private\n";
+/// assert_eq!(
+///     vec![LintIssue::ConflictingRules {
+///         pattern: "/private".into(),
+///         allow_line: 3,
+///         disallow_line: 2,
+///     }],
+///     lint(robots_body)
+/// );
+/// ```
+pub fn lint(robots_body: &str) -> Vec<LintIssue> {
+    let mut collector = LintCollector::default();
+    parse_robotstxt(robots_body, &mut collector);
+
+    let mut issues = collector.meta_directives;
+    for group in collector.groups.groups() {
+        check_group(group, &collector.raw_values, &mut issues);
+    }
+    issues.sort_by_key(LintIssue::line);
+    issues
+}
+
+/// A [GroupCollector] which also keeps the values of the rules as written, by
+/// line, and reports misplaced meta robots directives.
+#[derive(Default)]
+struct LintCollector {
+    groups: GroupCollector,
+    raw_values: HashMap<u32, String>,
+    meta_directives: Vec<LintIssue>,
+}
+
+impl LintCollector {
+    fn check_meta_directive(&mut self, line_num: u32, directive: &str) {
+        let directive = directive.trim().to_ascii_lowercase();
+        if META_ROBOTS_DIRECTIVES.contains(&directive.as_str()) {
+            self.meta_directives.push(LintIssue::MetaRobotsDirective {
+                directive,
+                line: line_num,
+            });
+        }
+    }
+}
+
+impl RobotsParseHandler for LintCollector {
+    fn handle_robots_start(&mut self) {
+        self.groups.handle_robots_start();
+        self.raw_values.clear();
+        self.meta_directives.clear();
+    }
+
+    fn handle_robots_end(&mut self) {
+        self.groups.handle_robots_end();
+    }
+
+    fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+        self.groups.handle_user_agent(line_num, user_agent);
+    }
+
+    fn handle_allow(&mut self, line_num: u32, value: &str) {
+        self.groups.handle_allow(line_num, value);
+    }
+
+    fn handle_disallow(&mut self, line_num: u32, value: &str) {
+        self.groups.handle_disallow(line_num, value);
+    }
+
+    fn handle_sitemap(&mut self, line_num: u32, value: &str) {
+        self.groups.handle_sitemap(line_num, value);
+    }
+
+    fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {
+        self.groups.handle_unknown_action(line_num, action, value);
+        self.check_meta_directive(line_num, action);
+    }
+
+    fn handle_parse_error(&mut self, line_num: u32, raw_line: &str) {
+        // A lone `noarchive` has no separator, so it's not a directive.
+        let content = raw_line.split('#').next().unwrap_or_default();
+        self.check_meta_directive(line_num, content);
+    }
+
+    fn handle_raw_value(&mut self, line_num: u32, raw_value: &str, _escaped_value: &str) {
+        self.raw_values.insert(line_num, raw_value.to_string());
+    }
+}
+
+/// Compares every rule of a group with the rules before it.
+fn check_group(group: &Group, raw_values: &HashMap<u32, String>, issues: &mut Vec<LintIssue>) {
+    for (index, rule) in group.rules.iter().enumerate() {
+        let earlier = &group.rules[..index];
+        check_duplicates_and_conflicts(earlier, rule, issues);
+        check_equivalent_encodings(earlier, rule, raw_values, issues);
+    }
+}
+
+/// Only the first earlier rule with the same pattern is reported against, so
+/// three identical rules make two issues, not three.
+fn check_duplicates_and_conflicts(earlier: &[Rule], rule: &Rule, issues: &mut Vec<LintIssue>) {
+    if let Some(first) = earlier
+        .iter()
+        .find(|other| other.kind == rule.kind && other.pattern == rule.pattern)
+    {
+        issues.push(LintIssue::DuplicateRule {
+            kind: rule.kind,
+            pattern: rule.pattern.clone(),
+            first_line: first.line,
+            line: rule.line,
+        });
+    } else if let Some(other) = earlier
+        .iter()
+        .find(|other| other.kind != rule.kind && other.pattern == rule.pattern)
+    {
+        let (allow_line, disallow_line) = match rule.kind {
+            RuleKind::Allow => (rule.line, other.line),
+            RuleKind::Disallow => (other.line, rule.line),
+        };
+        issues.push(LintIssue::ConflictingRules {
+            pattern: rule.pattern.clone(),
+            allow_line,
+            disallow_line,
+        });
+    }
+}
+
+/// Reports the first earlier rule with the same %-escaped pattern but written
+/// differently.
+fn check_equivalent_encodings(
+    earlier: &[Rule],
+    rule: &Rule,
+    raw_values: &HashMap<u32, String>,
+    issues: &mut Vec<LintIssue>,
+) {
+    let source = |rule: &Rule| {
+        raw_values
+            .get(&rule.line)
+            .cloned()
+            .unwrap_or_else(|| rule.pattern.clone())
+    };
+    let rule_source = source(rule);
+    if let Some(first) = earlier
+        .iter()
+        .find(|other| other.pattern == rule.pattern && source(other) != rule_source)
+    {
+        issues.push(LintIssue::EquivalentEncodings {
+            pattern: rule.pattern.clone(),
+            first_source: source(first),
+            first_line: first.line,
+            source: rule_source,
+            line: rule.line,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::*;
+
+    #[test]
+    fn test_duplicates_and_conflicts() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /a\n\
+        disallow: /a\n\
+        allow: /a\n\
+        disallow: /a\n\
+        allow: /b\n\
+        \n\
+        user-agent: BarBot\n\
+        disallow: /b\n\
+        disallow: /a\n";
+        assert_eq!(
+            vec![
+                LintIssue::DuplicateRule {
+                    kind: RuleKind::Disallow,
+                    pattern: "/a".into(),
+                    first_line: 2,
+                    line: 3,
+                },
+                LintIssue::ConflictingRules {
+                    pattern: "/a".into(),
+                    allow_line: 4,
+                    disallow_line: 2,
+                },
+                LintIssue::DuplicateRule {
+                    kind: RuleKind::Disallow,
+                    pattern: "/a".into(),
+                    first_line: 2,
+                    line: 5,
+                },
+            ],
+            lint(robotstxt)
+        );
+
+        // Rules are compared after %-escaping.
+        assert_eq!(
+            vec![
+                LintIssue::DuplicateRule {
+                    kind: RuleKind::Allow,
+                    pattern: "/caf%C3%A9".into(),
+                    first_line: 2,
+                    line: 3,
+                },
+                LintIssue::EquivalentEncodings {
+                    pattern: "/caf%C3%A9".into(),
+                    first_source: "/café".into(),
+                    first_line: 2,
+                    source: "/caf%c3%a9".into(),
+                    line: 3,
+                },
+            ],
+            lint("user-agent: *\nallow: /café\nallow: /caf%c3%a9\n")
+        );
+        assert!(lint("").is_empty());
+    }
+
+    #[test]
+    fn test_equivalent_encodings() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /caf%C3%A9\n\
+        allow: /café\n\
+        disallow: /caf%C3%A9\n\
+        \n\
+        user-agent: BarBot\n\
+        disallow: /café\n";
+        assert_eq!(
+            vec![
+                LintIssue::ConflictingRules {
+                    pattern: "/caf%C3%A9".into(),
+                    allow_line: 3,
+                    disallow_line: 2,
+                },
+                LintIssue::EquivalentEncodings {
+                    pattern: "/caf%C3%A9".into(),
+                    first_source: "/caf%C3%A9".into(),
+                    first_line: 2,
+                    source: "/café".into(),
+                    line: 3,
+                },
+                LintIssue::DuplicateRule {
+                    kind: RuleKind::Disallow,
+                    pattern: "/caf%C3%A9".into(),
+                    first_line: 2,
+                    line: 4,
+                },
+                LintIssue::EquivalentEncodings {
+                    pattern: "/caf%C3%A9".into(),
+                    first_source: "/café".into(),
+                    first_line: 3,
+                    source: "/caf%C3%A9".into(),
+                    line: 4,
+                },
+            ],
+            lint(robotstxt)
+        );
+    }
+
+    #[test]
+    fn test_meta_robots_directives() {
+        let robotstxt = "user-agent: *\n\
+        noarchive\n\
+        disallow: /a\n\
+        NoIndex: /private # Not here\n\
+        disallow: /a\n\
+        max-snippet: 50\n\
+        noodle: /\n\
+        nosnippet yes please\n";
+        let issues = lint(robotstxt);
+        assert_eq!(
+            vec![
+                LintIssue::MetaRobotsDirective {
+                    directive: "noarchive".into(),
+                    line: 2,
+                },
+                LintIssue::MetaRobotsDirective {
+                    directive: "noindex".into(),
+                    line: 4,
+                },
+                LintIssue::DuplicateRule {
+                    kind: RuleKind::Disallow,
+                    pattern: "/a".into(),
+                    first_line: 3,
+                    line: 5,
+                },
+                LintIssue::MetaRobotsDirective {
+                    directive: "max-snippet".into(),
+                    line: 6,
+                },
+            ],
+            issues
+        );
+        assert_eq!(
+            "line 4: 'noindex' is probably meant for a meta robots tag, robots.txt doesn't support it",
+            issues[1].to_string()
+        );
+        assert_eq!(
+            "line 5: rule for '/a' duplicates line 3",
+            issues[2].to_string()
+        );
+    }
+}
@@ -20,7 +20,8 @@
 /// Usage:
 ///     cargo run <local_path_to_robotstxt> <user_agent> <url>
 /// Arguments:
-/// local_path_to_robotstxt: local path to a file containing robots.txt records.
+/// local_path_to_robotstxt: local path to a file containing robots.txt records,
+/// or '-' to read the records from standard input.
 ///   For example: /home/users/username/robots.txt
 /// user_agent: a token to be matched against records in the robots.txt.
 ///   For example: Googlebot
@@ -31,6 +32,7 @@
 /// to access 'url' based on records in 'local_path_to_robotstxt'.
 use std::env;
 use std::fs;
+use std::io::{self, Read};
 
 use robotstxt::DefaultMatcher;
 
@@ -44,12 +46,25 @@ fn show_help(name: &str) {
         name
     );
     eprintln!("The URI must be %-encoded according to RFC3986.\n");
+    eprintln!("Use '-' as filename to read the robots.txt from standard input.\n");
     eprintln!(
         "Example:\n {} robots.txt FooBot http://example.com/foo\n",
         name
     );
 }
 
+/// Reads the robots.txt content from the given file, or from standard input
+/// if filename is '-'.
+fn read_robots_content(filename: &str) -> io::Result<String> {
+    if filename == "-" {
+        let mut robots_content = String::new();
+        io::stdin().read_to_string(&mut robots_content)?;
+        Ok(robots_content)
+    } else {
+        fs::read_to_string(filename)
+    }
+}
+
 fn main() {
     let mut args = env::args();
     match (args.next(), args.next(), args.next(), args.next()) {
@@ -59,7 +74,7 @@ fn main() {
             show_help(&execute);
         }
         (_, Some(filename), Some(user_agent), Some(url)) => {
-            if let Ok(robots_content) = fs::read_to_string(filename.clone()) {
+            if let Ok(robots_content) = read_robots_content(&filename) {
                 let user_agents: Vec<&str> = vec![&user_agent];
                 let mut matcher = DefaultMatcher::default();
                 let allowed = matcher.allowed_by_robots(&robots_content, user_agents, &url);
@@ -74,6 +89,8 @@ fn main() {
                 if robots_content.is_empty() {
                     println!("notice: robots file is empty so all user-agents are allowed");
                 }
+            } else if filename == "-" {
+                eprintln!("failed to read robots.txt from standard input");
             } else {
                 eprintln!("failed to read file \"{}\"", filename);
             }
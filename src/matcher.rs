@@ -17,7 +17,10 @@
 #![allow(unused_variables, dead_code)]
 
 use std::borrow::Cow;
+use std::fmt;
 
+use crate::collector::RuleKind;
+use crate::parser::{decode_unreserved, escape_pattern, RobotsTxtParser};
 use crate::RobotsParseHandler;
 
 /// Instead of just maintaining a Boolean indicating whether a given line has
@@ -32,6 +35,9 @@ use crate::RobotsParseHandler;
 struct Match {
     priority: i32,
     line: u32,
+    /// True if the match is the '$'-anchored directory pattern an
+    /// 'Allow: .../index.html' line is normalized to.
+    index_normalized: bool,
 }
 
 impl Default for Match {
@@ -43,12 +49,26 @@ impl Default for Match {
 impl Match {
     const NO_MATCH_PRIORITY: i32 = -1;
     pub fn new(priority: i32, line: u32) -> Match {
-        Match { priority, line }
+        Match {
+            priority,
+            line,
+            index_normalized: false,
+        }
     }
 
     pub fn set(&mut self, priority: i32, line: u32) {
         self.priority = priority;
         self.line = line;
+        self.index_normalized = false;
+    }
+
+    pub fn set_index_normalized(&mut self, priority: i32, line: u32) {
+        self.set(priority, line);
+        self.index_normalized = true;
+    }
+
+    pub fn index_normalized(&self) -> bool {
+        self.index_normalized
     }
 
     pub fn clear(&mut self) {
@@ -72,6 +92,160 @@ impl Match {
     }
 }
 
+/// An allow or disallow rule which matched the path of the last check, see
+/// [matching_rules](RobotsMatcher::matching_rules()).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MatchedRule {
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub priority: i32,
+    pub line: u32,
+    /// True if the rule is in a group naming one of our user-agents, false if
+    /// it is in a global group.
+    pub specific: bool,
+    /// True if the rule is an 'Allow: .../index.html' line which matched once
+    /// normalized to its directory, see
+    /// [with_index_normalization](RobotsMatcher::with_index_normalization()).
+    /// The pattern is then the normalized one, e.g. '/dir/$'.
+    pub index_normalized: bool,
+}
+
+/// Priority and line of the best match for a kind of rule. The priority is
+/// negative and the line 0 if no rule matched.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MatchPriority {
+    pub priority: i32,
+    pub line: u32,
+}
+
+impl From<&Match> for MatchPriority {
+    fn from(m: &Match) -> Self {
+        MatchPriority {
+            priority: m.priority(),
+            line: m.line(),
+        }
+    }
+}
+
+/// The best allow and disallow matches of the last check, for the global group
+/// and for the groups of our user-agents. These are the inputs of the decision,
+/// see [match_state](RobotsMatcher::match_state()).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MatchSnapshot {
+    pub allow_global: MatchPriority,
+    pub allow_specific: MatchPriority,
+    pub disallow_global: MatchPriority,
+    pub disallow_specific: MatchPriority,
+}
+
+/// One evaluation of an allow or disallow rule against the path of the last
+/// check, see [take_evaluation_log](RobotsMatcher::take_evaluation_log()).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EvalStep {
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub line: u32,
+    /// True if the rule is in a group naming one of our user-agents, false if
+    /// it is in a global group.
+    pub specific: bool,
+    /// Priority the rule matched with, negative if it didn't match.
+    pub priority: i32,
+    /// Best allow priority so far in the same (specific or global) groups,
+    /// including this step.
+    pub best_allow: i32,
+    /// Best disallow priority so far in the same (specific or global) groups,
+    /// including this step.
+    pub best_disallow: i32,
+}
+
+/// The outcome of a check, see [crawl_decision](RobotsMatcher::crawl_decision()).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CrawlDecision {
+    /// An allow rule matched the path and won over any matching disallow rule.
+    Allowed,
+    /// A disallow rule matched the path and won over any matching allow rule.
+    Disallowed,
+    /// No rule of the groups obeyed matched the path (empty patterns don't
+    /// count), so crawling is allowed by default.
+    NoRuleApplies,
+}
+
+/// Why a check came out the way it did, see
+/// [decision_reason](RobotsMatcher::decision_reason()).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DecisionReason {
+    /// A disallow rule of a group for our user-agent won.
+    SpecificDisallow,
+    /// An allow rule of a group for our user-agent won.
+    SpecificAllow,
+    /// A disallow rule of a global group won, as there is no group for our
+    /// user-agent.
+    GlobalDisallow,
+    /// An allow rule of a global group won, as there is no group for our
+    /// user-agent.
+    GlobalAllow,
+    /// There are groups for our user-agent, but none of their rules matched.
+    /// Global rules are ignored in this case, so the path is allowed.
+    SpecificGroupEmpty,
+    /// There is no group for our user-agent and no global rule matched, so the
+    /// path is allowed.
+    NoMatchDefaultAllow,
+    /// A pattern of the groups obeyed needed more matching work than allowed,
+    /// so the check gave the fallback verdict, see
+    /// [with_match_work_limit](RobotsMatcher::with_match_work_limit()).
+    WorkLimitExceeded,
+    /// The path is longer than the maximum length and was rejected without
+    /// matching any rule, so it is disallowed, see
+    /// [with_max_path_length](RobotsMatcher::with_max_path_length()).
+    PathTooLong,
+}
+
+/// How a tie between an allow and a disallow rule matching with the same
+/// priority is resolved, see [with_tie_break](RobotsMatcher::with_tie_break()).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum TieBreak {
+    /// The allow rule wins, as Google does.
+    #[default]
+    AllowWins,
+    /// The disallow rule wins, the conservative choice.
+    DisallowWins,
+}
+
+/// The verdict of a check in which a pattern of the groups obeyed needed more
+/// matching work than allowed, see [with_match_work_limit](RobotsMatcher::with_match_work_limit()).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum WorkLimitFallback {
+    /// The URL is allowed.
+    Allow,
+    /// The URL is disallowed, the conservative choice.
+    #[default]
+    Disallow,
+}
+
+/// What a check does with a path longer than the maximum length, see
+/// [with_max_path_length](RobotsMatcher::with_max_path_length()).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LongPathPolicy {
+    /// The path is cut to the maximum length, on a character boundary, and
+    /// matched as such.
+    Truncate,
+    /// The path isn't matched and the URL is disallowed.
+    Reject,
+}
+
+/// The error of [allowed_by_robots_bounded](RobotsMatcher::allowed_by_robots_bounded())
+/// when a check needs more work than its budget.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BudgetExceeded;
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "robots.txt check exceeded its work budget")
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
 #[derive(Default)]
 struct MatchHierarchy {
     global: Match,
@@ -85,6 +259,14 @@ impl MatchHierarchy {
     }
 }
 
+/// Lines of the first patterns which went over the work limit, in the global
+/// and in the specific groups.
+#[derive(Default)]
+struct WorkLimitLines {
+    global: Option<u32>,
+    specific: Option<u32>,
+}
+
 /// Create a RobotsMatcher with the default matching strategy.
 ///
 /// The default matching strategy is longest-match as opposed to the former internet draft
@@ -141,9 +323,95 @@ pub trait RobotsMatchStrategy {
     ///     Target::matches("/google/robotstxt/tree/abc", "/*/*/tree/master")
     /// );
     /// ```
+    ///
+    /// Patterns are plain prefixes: a directory-style pattern without trailing
+    /// slash also matches the directory itself and everything below it, as well
+    /// as any path merely sharing the prefix. A pattern with trailing slash
+    /// doesn't match the directory path without it.
+    /// ```rust
+    /// use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+    ///
+    /// type Target = LongestMatchRobotsMatchStrategy;
+    /// assert_eq!(true, Target::matches("/dir", "/dir"));
+    /// assert_eq!(true, Target::matches("/dir/", "/dir"));
+    /// assert_eq!(true, Target::matches("/dir/page.html", "/dir"));
+    /// assert_eq!(true, Target::matches("/directory", "/dir"));
+    /// assert_eq!(true, Target::matches("/dir/", "/dir/"));
+    /// assert_eq!(false, Target::matches("/dir", "/dir/"));
+    /// assert_eq!(false, Target::matches("/directory", "/dir/"));
+    /// ```
+    ///
+    /// Matching is done byte by byte, so a %-escaped sequence is nothing more
+    /// than three characters: a wildcard may start or end in the middle of it.
+    /// ```rust
+    /// use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+    ///
+    /// type Target = LongestMatchRobotsMatchStrategy;
+    /// assert_eq!(true, Target::matches("/%C3%A9", "/*9"));
+    /// assert_eq!(true, Target::matches("/%C3%A9", "/%C3*"));
+    /// assert_eq!(true, Target::matches("/%C3%A9", "/%C*A9$"));
+    /// assert_eq!(true, Target::matches("/caf%C3%A9/menu", "/*%A9/"));
+    /// assert_eq!(false, Target::matches("/%C3%A9", "/*%A8"));
+    /// assert_eq!(false, Target::matches("/%C3%A9", "/%C3$"));
+    /// ```
+    ///
+    /// No decoding of any kind happens either: in particular a `+` in the query
+    /// is a literal `+`, not a space.
+    /// ```rust
+    /// use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+    ///
+    /// type Target = LongestMatchRobotsMatchStrategy;
+    /// assert_eq!(true, Target::matches("/a?b+c", "/a?b+c"));
+    /// assert_eq!(false, Target::matches("/a?b%20c", "/a?b+c"));
+    /// assert_eq!(false, Target::matches("/a?b+c", "/a?b%20c"));
+    /// ```
+    ///
+    /// A wildcard can't be escaped: `*` in a pattern always matches any sequence
+    /// of characters, so a pattern can't match a literal `*` alone, and `%2A` is
+    /// just three characters which never match a `*` of the path. See
+    /// [with_escaped_asterisks](RobotsMatcher::with_escaped_asterisks()) for
+    /// an extension.
+    /// ```rust
+    /// use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+    ///
+    /// type Target = LongestMatchRobotsMatchStrategy;
+    /// assert_eq!(true, Target::matches("/a*b", "/a*b"));
+    /// assert_eq!(true, Target::matches("/axyzb", "/a*b"));
+    /// assert_eq!(false, Target::matches("/a*b", "/a%2Ab"));
+    /// assert_eq!(true, Target::matches("/a%2Ab", "/a%2Ab"));
+    /// ```
+    ///
+    /// There is no recursive glob: `**` is just two wildcards, which match the
+    /// same as a single one, including across `/`.
+    /// ```rust
+    /// use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+    ///
+    /// type Target = LongestMatchRobotsMatchStrategy;
+    /// assert_eq!(true, Target::matches("/a/b/c", "/**"));
+    /// assert_eq!(true, Target::matches("/a/b/c", "/**/c"));
+    /// assert_eq!(true, Target::matches("/a/x/y/c", "/a/**/c"));
+    /// assert_eq!(false, Target::matches("/a/c", "/a/**/c"));
+    /// ```
+    ///
+    /// A trailing `*` matches any sequence of characters, the empty one
+    /// included, so it changes nothing to what a pattern matches: `/a*` matches
+    /// `/a` itself, just like `/a`.
+    /// ```rust
+    /// use robotstxt::matcher::{LongestMatchRobotsMatchStrategy, RobotsMatchStrategy};
+    ///
+    /// type Target = LongestMatchRobotsMatchStrategy;
+    /// assert_eq!(true, Target::matches("/a", "/a*"));
+    /// assert_eq!(true, Target::matches("/ab", "/a*"));
+    /// assert_eq!(true, Target::matches("/a/b", "/a*"));
+    /// assert_eq!(true, Target::matches("/a", "/a*$"));
+    /// assert_eq!(false, Target::matches("/", "/a*"));
+    /// assert_eq!(false, Target::matches("/b/a", "/a*"));
+    /// ```
     fn matches(path: &str, pattern: &str) -> bool {
+        let path = path.as_bytes();
+        let pattern = pattern.as_bytes();
         let pathlen = path.len();
-        let mut pos = Vec::with_capacity(pathlen + 1);
+        let mut pos = vec![0; pathlen + 1];
 
         // The pos[] array holds a sorted list of indexes of 'path', with length
         // 'numpos'.  At the start and end of each iteration of the main loop below,
@@ -152,25 +420,27 @@ pub trait RobotsMatchStrategy {
         // return false. If we reach the end of 'pattern' with at least one element
         // in pos[], return true.
         let mut numpos: usize = 1;
-        pos.insert(0, 0);
 
-        for (index, pat) in pattern.chars().enumerate() {
-            if pat == '$' && index + 1 == pattern.len() {
+        for (index, &pat) in pattern.iter().enumerate() {
+            if pat == b'$' && index + 1 == pattern.len() {
                 return pos[numpos - 1] == pathlen;
             }
 
-            if pat == '*' {
+            if pat == b'*' {
+                // Consecutive wildcards match the same as a single one.
+                if index > 0 && pattern[index - 1] == b'*' {
+                    continue;
+                }
                 numpos = pathlen - pos[0] + 1;
                 for i in 1..numpos {
-                    pos.insert(i, pos[i - 1] + 1);
+                    pos[i] = pos[i - 1] + 1;
                 }
             } else {
                 // Includes '$' when not at end of pattern.
                 let mut new_numpos = 0;
                 for i in 0..numpos {
-                    // TODO Optimize chars().nth() ?
-                    if pos[i] < pathlen && path.chars().nth(pos[i]) == Some(pat) {
-                        pos.insert(new_numpos, pos[i] + 1);
+                    if pos[i] < pathlen && path[pos[i]] == pat {
+                        pos[new_numpos] = pos[i] + 1;
                         new_numpos += 1;
                     }
                 }
@@ -208,6 +478,74 @@ impl RobotsMatchStrategy for LongestMatchRobotsMatchStrategy {
     }
 }
 
+/// A match strategy delegating to closures, to try out other matching rules
+/// without a type of their own. Each closure gets the path and the pattern and
+/// returns the match priority, or a negative value if the pattern doesn't
+/// match. The default strategy delegates to [LongestMatchRobotsMatchStrategy].
+/// ```rust
+/// use robotstxt::matcher::{ClosureMatchStrategy, RobotsMatchStrategy, RobotsMatcher};
+///
+/// // Case-insensitive matching.
+/// let match_ignoring_case = |path: &str, pattern: &str| {
+///     let pattern = pattern.to_ascii_lowercase();
+///     if ClosureMatchStrategy::matches(&path.to_ascii_lowercase(), &pattern) {
+///         pattern.len() as i32
+///     } else {
+///         -1
+///     }
+/// };
+/// let mut matcher = RobotsMatcher::default().with_match_strategy(ClosureMatchStrategy::new(
+///     match_ignoring_case,
+///     match_ignoring_case,
+/// ));
+/// let robots_body = "user-agent: FooBot\n\
+///                    disallow: /private\n";
+/// assert_eq!(
+///     false,
+///     matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/PRIVATE")
+/// );
+/// ```
+pub struct ClosureMatchStrategy {
+    match_allow: Box<MatchFn>,
+    match_disallow: Box<MatchFn>,
+}
+
+/// Returns the match priority of a pattern against a path.
+type MatchFn = dyn Fn(&str, &str) -> i32 + Send + Sync;
+
+impl ClosureMatchStrategy {
+    /// Creates a strategy calling `match_allow` for allow rules and
+    /// `match_disallow` for disallow rules.
+    pub fn new(
+        match_allow: impl Fn(&str, &str) -> i32 + Send + Sync + 'static,
+        match_disallow: impl Fn(&str, &str) -> i32 + Send + Sync + 'static,
+    ) -> Self {
+        ClosureMatchStrategy {
+            match_allow: Box::new(match_allow),
+            match_disallow: Box::new(match_disallow),
+        }
+    }
+}
+
+impl Default for ClosureMatchStrategy {
+    fn default() -> Self {
+        ClosureMatchStrategy::new(
+            |path, pattern| LongestMatchRobotsMatchStrategy.match_allow(path, pattern),
+            |path, pattern| LongestMatchRobotsMatchStrategy.match_disallow(path, pattern),
+        )
+    }
+}
+
+impl RobotsMatchStrategy for ClosureMatchStrategy {
+    fn match_allow(&self, path: &str, pattern: &str) -> i32 {
+        (self.match_allow)(path, pattern)
+    }
+
+    fn match_disallow(&self, path: &str, pattern: &str) -> i32 {
+        (self.match_disallow)(path, pattern)
+    }
+}
+
 /// RobotsMatcher - matches robots.txt against URLs.
 ///
 /// The Matcher uses a default match strategy for Allow/Disallow patterns which
@@ -230,6 +568,8 @@ pub struct RobotsMatcher<'a, S: RobotsMatchStrategy> {
     seen_specific_agent: bool,
     /// True if we ever saw a block for our agent.
     ever_seen_specific_agent: bool,
+    /// Length of the longest user-agent token matching our agent we saw so far.
+    specific_agent_len: usize,
     /// True if saw any key: value pair.
     seen_separator: bool,
     /// The path we want to pattern match. Not owned and only a valid pointer
@@ -239,193 +579,1380 @@ pub struct RobotsMatcher<'a, S: RobotsMatchStrategy> {
     /// pointer during the lifetime of [allowed_by_robots](RobotsMatcher::allowed_by_robots()) calls.
     user_agents: Vec<&'a str>,
     match_strategy: S,
+    /// True if a pattern must end on a path segment boundary to match.
+    match_whole_segments: bool,
+    /// True if only the groups of the longest matching user-agent apply.
+    select_longest_agent: bool,
+    /// True if a user-agent line may list several agents separated by commas.
+    split_agent_lists: bool,
+    /// True if quotes around user-agent, allow and disallow values are stripped.
+    strip_quotes: bool,
+    /// True if allow rules are skipped, leaving only the disallow rules.
+    ignore_allow_rules: bool,
+    /// True if parsing stops at the end of the first group for our agent.
+    stop_after_specific_group: bool,
+    /// True once the group for our agent is over and parsing should stop.
+    specific_group_done: bool,
+    /// True if every matching rule is recorded in 'matched_rules'.
+    record_matches: bool,
+    matched_rules: Vec<MatchedRule>,
+    /// True if every rule evaluation is logged in 'evaluation_log'.
+    log_evaluation: bool,
+    evaluation_log: Vec<EvalStep>,
+    /// True if runs of '/' in the path are collapsed before matching.
+    collapse_slashes: bool,
+    /// True if %-encoded unreserved characters are decoded before matching.
+    decode_unreserved: bool,
+    /// True if a '*' in the path is matched as '%2A'.
+    escaped_asterisks: bool,
+    /// True if the Google-specific extensions of the robots.txt draft are off.
+    strict_rfc: bool,
+    /// True if allow rules for index files don't also allow their directory.
+    skip_index_normalization: bool,
+    /// Prefixes of the file names an allow rule is normalized for, or None for
+    /// the default 'index.htm'.
+    index_files: Option<Vec<String>>,
+    /// True if the query parameters of the path and patterns are sorted before matching.
+    sort_query_params: bool,
+    /// Maximum length of the path and what to do with longer ones, if any.
+    max_path_length: Option<(usize, LongPathPolicy)>,
+    /// True if the path of the current check was rejected for its length.
+    path_rejected: bool,
+    /// Bound on the matching work of a pattern, if any.
+    match_work_limit: Option<usize>,
+    work_limit_fallback: WorkLimitFallback,
+    /// The patterns which went over the work limit during the last check.
+    work_limit_lines: WorkLimitLines,
+    /// Work left for the current check, only during a bounded check.
+    work_budget: Option<usize>,
+    /// Bytes of the robots.txt already taken from the work budget.
+    budget_parsed_bytes: usize,
+    /// True if the current bounded check went over its work budget.
+    budget_exceeded: bool,
+    tie_break: TieBreak,
 }
 
 impl<'a, S: RobotsMatchStrategy> RobotsMatcher<'a, S> {
-    /// Initialize next path and user-agents to check. Path must contain only the
-    /// path, params, and query (if any) of the url and must start with a '/'.
-    fn init_user_agents_and_path(&mut self, user_agents: Vec<&'a str>, path: Cow<'a, str>) {
-        self.path = path;
-        self.user_agents = user_agents;
-    }
-
-    /// Returns true if 'url' is allowed to be fetched by any member of the
-    /// "user_agents" vector. 'url' must be %-encoded according to RFC3986.
-    pub fn allowed_by_robots(
-        &mut self,
-        robots_body: &'a str,
-        user_agents: Vec<&'a str>,
-        url: &'a str,
-    ) -> bool
+    /// Creates a matcher following the robots.txt draft to the letter, without
+    /// any of the Google-specific extensions, for comparison studies. Compared
+    /// to the default matcher:
+    /// - typos in keys, such as `disalow` or `useragent`, aren't recognized;
+    /// - a key and value separated by whitespace instead of a colon, such as
+    ///   `Disallow /a`, isn't recognized;
+    /// - a byte order mark at the start of the robots.txt isn't skipped, so a
+    ///   directive on the first line isn't recognized. By default both a
+    ///   decoded U+FEFF and its UTF-8 bytes read as characters are skipped,
+    ///   while the original library only skips the latter;
+    /// - only a user-agent of exactly `*` names the global group, `* foo` doesn't;
+    /// - `Allow: /dir/index.html` doesn't also allow `/dir/`.
+    ///
+    /// The other options keep their default value and can still be set.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: * all\n\
+    ///                    disalow: /a\n\
+    ///                    disallow /b\n\
+    ///                    disallow: /c\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert_eq!(false, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/a"));
+    /// assert_eq!(false, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/b"));
+    /// assert_eq!(false, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/c"));
+    ///
+    /// let robots_body = "user-agent: *\n\
+    ///                    disalow: /a\n\
+    ///                    disallow /b\n\
+    ///                    disallow: /c\n";
+    /// let mut matcher = DefaultMatcher::strict_rfc();
+    /// assert_eq!(true, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/a"));
+    /// assert_eq!(true, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/b"));
+    /// assert_eq!(false, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/c"));
+    /// ```
+    pub fn strict_rfc() -> Self
     where
-        Self: RobotsParseHandler,
+        Self: Default,
     {
-        // The url is not normalized (escaped, percent encoded) here because the user
-        // is asked to provide it in escaped form already.
-        let path = super::get_path_params_query(url);
-        self.init_user_agents_and_path(user_agents, path);
-        super::parse_robotstxt(robots_body, self);
-        !self.disallow()
+        RobotsMatcher {
+            strict_rfc: true,
+            skip_index_normalization: true,
+            ..Default::default()
+        }
     }
 
-    /// Do robots check for 'url' when there is only one user agent. 'url' must
-    /// be %-encoded according to RFC3986.
-    pub fn one_agent_allowed_by_robots(
-        &mut self,
-        robots_txt: &'a str,
-        user_agent: &'a str,
-        url: &'a str,
-    ) -> bool
-    where
-        Self: RobotsParseHandler,
-    {
-        self.allowed_by_robots(robots_txt, vec![user_agent], url)
+    /// Only let a pattern match if it ends on a path segment boundary of the
+    /// URI, that is at the end of the path or right before a '/', '?' or ';'.
+    /// With this, `Disallow: /dir` blocks `/dir` and `/dir/page.html` but no
+    /// longer `/directory`. Patterns ending with '/', '*' or '$' are unaffected.
+    ///
+    /// This is not part of Google's matching and is disabled by default.
+    pub fn with_whole_segment_matching(mut self, enabled: bool) -> Self {
+        self.match_whole_segments = enabled;
+        self
     }
 
-    /// Returns true if we are disallowed from crawling a matching URI.
-    fn disallow(&self) -> bool {
-        if self.allow.specific.priority() > 0 || self.disallow.specific.priority() > 0 {
-            return self.disallow.specific.priority() > self.allow.specific.priority();
-        }
+    /// When several of our user-agents have a group in the robots.txt, only obey
+    /// the groups of the most specific one, that is the longest user-agent token.
+    /// For example, checking for both `FooBot` and `FooBot-Image` in a robots.txt
+    /// with a group for each only applies the rules of the `FooBot-Image` group.
+    ///
+    /// By default the rules of all matching groups are merged.
+    pub fn with_longest_agent_selection(mut self, enabled: bool) -> Self {
+        self.select_longest_agent = enabled;
+        self
+    }
 
-        if self.ever_seen_specific_agent {
-            // Matching group for user-agent but either without disallow or empty one,
-            // i.e. priority == 0.
-            return false;
-        }
+    /// Accept several user-agents separated by commas on a single user-agent
+    /// line, as in `User-agent: Googlebot, Bingbot`, each of them heading the
+    /// group.
+    ///
+    /// This is not standard: by default, like Google, only the first agent of
+    /// such a line is recognized, as the user-agent ends at the comma.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: FooBot, BarBot\n\
+    ///                    disallow: /\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/"));
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "BarBot", "/"));
+    /// let mut matcher = DefaultMatcher::default().with_comma_separated_agents(true);
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "BarBot", "/"));
+    /// ```
+    pub fn with_comma_separated_agents(mut self, enabled: bool) -> Self {
+        self.split_agent_lists = enabled;
+        self
+    }
 
-        if self.disallow.global.priority() > 0 || self.allow.global.priority() > 0 {
-            return self.disallow.global.priority() > self.allow.global.priority();
-        }
+    /// Skip the allow rules, so that checks only consider the disallow rules,
+    /// see [disallowed_ignoring_allow](crate::disallowed_ignoring_allow). The
+    /// allow rules still end the user-agent lines of a group. Disabled by
+    /// default.
+    pub fn with_allow_rules_ignored(mut self, enabled: bool) -> Self {
+        self.ignore_allow_rules = enabled;
+        self
+    }
 
-        false
+    /// Stop parsing the robots.txt as soon as the first group naming one of our
+    /// user-agents is over, which saves time on huge files when only our own
+    /// group matters.
+    ///
+    /// Beware that groups for the same user-agent are merged, so any later group
+    /// for our agent is missed in this mode. Sitemaps and other directives after
+    /// that point are skipped as well. Disabled by default.
+    pub fn with_stop_after_specific_group(mut self, enabled: bool) -> Self {
+        self.stop_after_specific_group = enabled;
+        self
     }
 
-    /// Returns true if any user-agent was seen.
-    fn seen_any_agent(&self) -> bool {
-        self.seen_global_agent || self.seen_specific_agent
+    /// Record every allow and disallow rule matching the path, not only the ones
+    /// deciding the outcome, see [matching_rules](RobotsMatcher::matching_rules()).
+    ///
+    /// Disabled by default to keep checks free of the allocations this needs.
+    pub fn with_match_recording(mut self, enabled: bool) -> Self {
+        self.record_matches = enabled;
+        self
     }
 
-    /// Extract the matchable part of a user agent string, essentially stopping at
-    /// the first invalid character.
-    /// Example: 'Googlebot/2.1' becomes 'Googlebot'
-    fn extract_user_agent(user_agent: &str) -> &str {
-        // Allowed characters in user-agent are [a-zA-Z_-].
-        if let Some(end) =
-            user_agent.find(|c: char| !(c.is_ascii_alphabetic() || c == '-' || c == '_'))
-        {
-            &user_agent[..end]
-        } else {
-            user_agent
-        }
+    /// Collapse runs of consecutive slashes in the path of the checked URL into
+    /// a single one before matching, as many crawlers do, so that
+    /// `http://example.com//a///b` is checked as `/a/b`. The query and the
+    /// patterns are left as they are.
+    ///
+    /// Google matches the path literally, so this is disabled by default.
+    pub fn with_slash_collapsing(mut self, enabled: bool) -> Self {
+        self.collapse_slashes = enabled;
+        self
     }
 
-    /// Verifies that the given user agent is valid to be matched against
-    /// robots.txt. Valid user agent strings only contain the characters
-    /// [a-zA-Z_-].
-    pub fn is_valid_user_agent_to_obey(user_agent: &str) -> bool {
-        !user_agent.is_empty() && Self::extract_user_agent(user_agent) == user_agent
+    /// Decode the %-escape sequences of unreserved characters (ALPHA, DIGIT,
+    /// '-', '.', '_' and '~') in both the path and the patterns before
+    /// matching, as RFC 3986 normalization does, so that `/%70rivate` is
+    /// matched by `Disallow: /private` and the other way around. Other escape
+    /// sequences, like `%2F`, are still matched literally.
+    ///
+    /// Disabled by default, paths and patterns are matched as they are.
+    pub fn with_unreserved_decoding(mut self, enabled: bool) -> Self {
+        self.decode_unreserved = enabled;
+        self
     }
 
-    /// Returns true if we are disallowed from crawling a matching URI. Ignores any
-    /// rules specified for the default user agent, and bases its results only on
-    /// the specified user agents.
-    fn disallow_ignore_global(&self) -> bool {
-        if self.allow.specific.priority() > 0 || self.disallow.specific.priority() > 0 {
-            return self.disallow.specific.priority() > self.allow.specific.priority();
-        }
-        false
+    /// Let `%2A` in a pattern match a literal `*` in the path, as well as `%2A`,
+    /// so that a rule can target a literal asterisk while `*` stays a wildcard:
+    /// `Disallow: /a%2Ab` blocks `/a*b` but not `/axb`.
+    ///
+    /// Google has no way to escape a wildcard, so this is disabled by default.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /a%2Ab\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/a*b"));
+    ///
+    /// let mut matcher = DefaultMatcher::default().with_escaped_asterisks(true);
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/a*b"));
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/a%2Ab"));
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/axb"));
+    /// ```
+    pub fn with_escaped_asterisks(mut self, enabled: bool) -> Self {
+        self.escaped_asterisks = enabled;
+        self
     }
 
-    /// Returns the line that matched or 0 if none matched.
-    fn matching_line(&self) -> u32 {
-        if self.ever_seen_specific_agent {
-            return Match::higher_priority_match(&self.disallow.specific, &self.allow.specific)
-                .line();
-        }
-        Match::higher_priority_match(&self.disallow.global, &self.allow.global).line()
+    /// Sort the parameters of the query, split on '&', in both the path and the
+    /// patterns before matching, so that `Disallow: /p?a=1&b=2` also blocks
+    /// `/p?b=2&a=1`. A '$' ending a pattern stays at the end. Parameters
+    /// holding a '*' are sorted as they are written, so wildcards spanning
+    /// several parameters may no longer match.
+    ///
+    /// This is an extension to the robots.txt draft: Google matches the query
+    /// literally, so this is disabled by default.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /p?a=1&b=2\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/p?b=2&a=1"));
+    /// let mut matcher = DefaultMatcher::default().with_query_param_sorting(true);
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/p?b=2&a=1"));
+    /// ```
+    pub fn with_query_param_sorting(mut self, enabled: bool) -> Self {
+        self.sort_query_params = enabled;
+        self
     }
-}
 
-impl<S: RobotsMatchStrategy> RobotsParseHandler for RobotsMatcher<'_, S> {
-    fn handle_robots_start(&mut self) {
-        // This is a new robots.txt file, so we need to reset all the instance member
-        // variables. We do it in the same order the instance member variables are
-        // declared, so it's easier to keep track of which ones we have (or maybe
-        // haven't!) done.
-        self.allow.clear();
-        self.disallow.clear();
+    /// Let an allow rule for an index file also allow its directory, so that
+    /// `Allow: /dir/index.html` allows `/dir/` as well, as Google does. Only
+    /// `index.htm` and `index.html` are index files by default, see
+    /// [with_index_files](Self::with_index_files()).
+    ///
+    /// Enabled by default.
+    pub fn with_index_normalization(mut self, enabled: bool) -> Self {
+        self.skip_index_normalization = !enabled;
+        self
+    }
 
-        self.seen_global_agent = false;
-        self.seen_specific_agent = false;
-        self.ever_seen_specific_agent = false;
-        self.seen_separator = false;
+    /// Set the file names for which an allow rule also allows the directory,
+    /// see [with_index_normalization](Self::with_index_normalization()), which
+    /// they only apply with. As for the default `index.htm`, a rule's last path
+    /// segment only needs to start with one of them, so `index.htm` covers
+    /// `index.html` too.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /\n\
+    ///                    allow: /a/index.php\n\
+    ///                    allow: /b/default.htm\n\
+    ///                    allow: /c/index.html\n";
+    /// let mut matcher =
+    ///     DefaultMatcher::default().with_index_files(&["index.php", "default.htm"]);
+    /// assert_eq!(true, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/a/"));
+    /// assert_eq!(true, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/b/"));
+    /// assert_eq!(false, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/c/"));
+    /// ```
+    pub fn with_index_files(mut self, index_files: &[&str]) -> Self {
+        self.index_files = Some(
+            index_files
+                .iter()
+                .filter(|index_file| !index_file.is_empty())
+                .map(|index_file| index_file.to_string())
+                .collect(),
+        );
+        self
     }
 
-    fn handle_robots_end(&mut self) {}
+    /// Use `match_strategy` to match the patterns of allow and disallow rules,
+    /// instead of the default value of the strategy type.
+    pub fn with_match_strategy(mut self, match_strategy: S) -> Self {
+        self.match_strategy = match_strategy;
+        self
+    }
 
-    fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
-        if self.seen_separator {
-            self.seen_specific_agent = false;
-            self.seen_global_agent = false;
-            self.seen_separator = false;
-        }
+    /// Choose which of an allow and a disallow rule matching with the same
+    /// priority wins. Defaults to [TieBreak::AllowWins], like Google.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    /// use robotstxt::matcher::TieBreak;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    allow: /x\n\
+    ///                    disallow: /x\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert_eq!(true, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/x"));
+    /// let mut matcher = DefaultMatcher::default().with_tie_break(TieBreak::DisallowWins);
+    /// assert_eq!(false, matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/x"));
+    /// ```
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
 
-        // Google-specific optimization: a '*' followed by space and more characters
-        // in a user-agent record is still regarded a global rule.
-        if !user_agent.is_empty()
-            && user_agent.starts_with('*')
-            && (user_agent.len() == 1 || user_agent[1..].starts_with(char::is_whitespace))
-        {
-            self.seen_global_agent = true;
-        } else {
-            let user_agent = Self::extract_user_agent(user_agent);
-            for agent in &self.user_agents {
-                if user_agent.eq_ignore_ascii_case(&agent) {
-                    self.ever_seen_specific_agent = true;
-                    self.seen_specific_agent = true;
-                    break;
-                }
-            }
-        }
+    /// Strip a pair of surrounding quotes from user-agent, allow and disallow
+    /// values, so that `User-agent: "FooBot"` names `FooBot`, see
+    /// [with_quote_stripping](RobotsTxtParser::with_quote_stripping()).
+    ///
+    /// Google takes quotes literally, so this is disabled by default.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: \"FooBot\"\n\
+    ///                    disallow: \"/private\"\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/private"));
+    /// let mut matcher = DefaultMatcher::default().with_quote_stripping(true);
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/private"));
+    /// ```
+    pub fn with_quote_stripping(mut self, enabled: bool) -> Self {
+        self.strip_quotes = enabled;
+        self
     }
 
-    fn handle_allow(&mut self, line_num: u32, value: &str) {
-        if !self.seen_any_agent() {
-            return;
-        }
+    /// Bound the length of the paths checked, in bytes, as extremely long paths
+    /// are slow to match against wildcard patterns. A longer path is handled
+    /// according to `policy`: truncated to `max_length`, so that only rules
+    /// matching its start apply, or rejected as disallowed, which
+    /// [decision_reason](Self::decision_reason()) reports as
+    /// [DecisionReason::PathTooLong]. Certain browsers
+    /// limit the URL length to 2083 bytes, a sensible maximum.
+    ///
+    /// There is no maximum by default, paths of any length are matched.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    /// use robotstxt::matcher::{DecisionReason, LongPathPolicy};
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /*.php$\n\
+    ///                    disallow: /private\n";
+    /// let url = format!("/{}.php", "a".repeat(3000));
+    /// let mut matcher = DefaultMatcher::default().with_max_path_length(2083, LongPathPolicy::Truncate);
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", &url));
+    /// let url = format!("/private/{}", "a".repeat(3000));
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", &url));
+    ///
+    /// let mut matcher = DefaultMatcher::default().with_max_path_length(2083, LongPathPolicy::Reject);
+    /// let url = format!("/{}", "a".repeat(3000));
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", &url));
+    /// assert_eq!(DecisionReason::PathTooLong, matcher.decision_reason());
+    /// ```
+    pub fn with_max_path_length(mut self, max_length: usize, policy: LongPathPolicy) -> Self {
+        self.max_path_length = Some((max_length, policy));
+        self
+    }
 
-        self.seen_separator = true;
-        let priority = self.match_strategy.match_disallow(&self.path, value);
-        if priority >= 0 {
-            if self.seen_specific_agent {
-                if self.allow.specific.priority() < priority {
-                    self.allow.specific.set(priority, line_num);
-                }
-            } else if self.allow.global.priority() < priority {
-                self.allow.global.set(priority, line_num);
-            }
-        } else {
-            // Google-specific optimization: 'index.htm' and 'index.html' are normalized to '/'.
-            let slash_pos = value.rfind('/');
+    /// Bound the work matching a pattern with wildcards may take, to protect
+    /// against untrusted robots.txt files. The work is counted as the length
+    /// of the pattern times the length of the path plus one, which bounds both
+    /// the time and the memory of matching. A pattern over the limit isn't
+    /// matched. If it is in a group the check obeys, the whole check gives
+    /// `fallback` instead of its verdict, see
+    /// [work_limit_exceeded](Self::work_limit_exceeded()) and
+    /// [DecisionReason::WorkLimitExceeded].
+    ///
+    /// There is no limit by default, patterns are matched whatever the work,
+    /// as in the original library.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    /// use robotstxt::matcher::WorkLimitFallback;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /*a*b*c\n";
+    /// let mut matcher = DefaultMatcher::default().with_match_work_limit(50, WorkLimitFallback::Allow);
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/abc"));
+    /// assert!(!matcher.work_limit_exceeded());
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/aaaaaaaaaaabc"));
+    /// assert!(matcher.work_limit_exceeded());
+    /// ```
+    pub fn with_match_work_limit(mut self, max_work: usize, fallback: WorkLimitFallback) -> Self {
+        self.match_work_limit = Some(max_work);
+        self.work_limit_fallback = fallback;
+        self
+    }
 
-            if let Some(slash_pos) = slash_pos {
-                if value[slash_pos..].starts_with("/index.htm") {
-                    let new_pattern = format!("{}{}", &value[..(slash_pos + 1)], "$");
-                    self.handle_allow(line_num, &new_pattern);
-                }
-            }
+    /// Returns true if a pattern of the groups obeyed needed more matching work
+    /// than allowed during the last check, which then gave the fallback
+    /// verdict, see [with_match_work_limit](Self::with_match_work_limit()).
+    /// Patterns of global groups ignored for a specific group don't count.
+    pub fn work_limit_exceeded(&self) -> bool {
+        self.work_limit_line().is_some()
+    }
+
+    /// Returns the line of the first pattern of the groups obeyed which went
+    /// over the work limit, if any.
+    fn work_limit_line(&self) -> Option<u32> {
+        if self.ever_seen_specific_agent {
+            return self.work_limit_lines.specific;
         }
+        self.work_limit_lines.global
     }
 
-    fn handle_disallow(&mut self, line_num: u32, value: &str) {
+    /// Log every evaluation of an allow or disallow rule of the groups naming
+    /// our user-agents and of the global groups, matching or not, with the best
+    /// priorities so far, see
+    /// [take_evaluation_log](RobotsMatcher::take_evaluation_log()). Rules are
+    /// evaluated as they are read, before it is known whether a group names
+    /// one of our agents, so the log also holds the evaluations of global rules
+    /// which end up ignored: [EvalStep::specific] tells them apart.
+    ///
+    /// Disabled by default to keep checks free of the allocations this needs.
+    pub fn with_evaluation_log(mut self, enabled: bool) -> Self {
+        self.log_evaluation = enabled;
+        self
+    }
+
+    /// Returns the evaluations of the last check, in the order they happened,
+    /// leaving the log empty. Replaying it shows how the longest match wins.
+    ///
+    /// Always empty unless logging was enabled with
+    /// [with_evaluation_log](RobotsMatcher::with_evaluation_log()).
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let mut matcher = DefaultMatcher::default().with_evaluation_log(true);
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /\n\
+    ///                    allow: /fish\n\
+    ///                    disallow: /cat\n";
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/fish"));
+    /// let steps: Vec<_> = matcher
+    ///     .take_evaluation_log()
+    ///     .iter()
+    ///     .map(|step| (step.pattern.clone(), step.priority, step.best_allow, step.best_disallow))
+    ///     .collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         ("/".to_string(), 1, -1, 1),
+    ///         ("/fish".to_string(), 5, 5, 1),
+    ///         ("/cat".to_string(), -1, 5, 1),
+    ///     ],
+    ///     steps
+    /// );
+    /// assert!(matcher.take_evaluation_log().is_empty());
+    /// ```
+    pub fn take_evaluation_log(&mut self) -> Vec<EvalStep> {
+        std::mem::take(&mut self.evaluation_log)
+    }
+
+    /// Returns all allow and disallow rules of the groups we obey which matched
+    /// the URL of the last check, in robots.txt order, with the priority each
+    /// of them matched with. Rules of the global groups are left out if a
+    /// group names one of our user-agents, as they are ignored then.
+    ///
+    /// Always empty unless recording was enabled with
+    /// [with_match_recording](RobotsMatcher::with_match_recording()).
+    /// ```rust
+    /// use robotstxt::collector::RuleKind;
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let mut matcher = DefaultMatcher::default().with_match_recording(true);
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /\n\
+    ///                    allow: /fish\n\
+    ///                    disallow: /cat\n";
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/fish"));
+    /// let rules: Vec<_> = matcher
+    ///     .matching_rules()
+    ///     .iter()
+    ///     .map(|rule| (rule.kind, rule.pattern.as_str(), rule.priority, rule.line))
+    ///     .collect();
+    /// assert_eq!(
+    ///     vec![(RuleKind::Disallow, "/", 1, 2), (RuleKind::Allow, "/fish", 5, 3)],
+    ///     rules
+    /// );
+    /// ```
+    pub fn matching_rules(&self) -> &[MatchedRule] {
+        &self.matched_rules
+    }
+
+    /// Returns the best allow and disallow matches of the last check.
+    ///
+    /// Rules of the groups of our user-agents take precedence over the global
+    /// ones: the global matches only decide if no group named our user-agents.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let mut matcher = DefaultMatcher::default();
+    /// let robots_body = "user-agent: *\n\
+    ///                    disallow: /\n\
+    ///                    user-agent: FooBot\n\
+    ///                    allow: /fish\n";
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/fish"));
+    /// let state = matcher.match_state();
+    /// assert_eq!((1, 2), (state.disallow_global.priority, state.disallow_global.line));
+    /// assert_eq!((5, 4), (state.allow_specific.priority, state.allow_specific.line));
+    /// assert!(state.allow_global.priority < 0);
+    /// assert!(state.disallow_specific.priority < 0);
+    /// ```
+    pub fn match_state(&self) -> MatchSnapshot {
+        MatchSnapshot {
+            allow_global: (&self.allow.global).into(),
+            allow_specific: (&self.allow.specific).into(),
+            disallow_global: (&self.disallow.global).into(),
+            disallow_specific: (&self.disallow.specific).into(),
+        }
+    }
+
+    /// Returns true if, at this point of parsing, the matcher is inside a group
+    /// which applies to our user-agents, either naming one of them or global.
+    /// Rules seen while this is false are ignored.
+    ///
+    /// This lets a [RobotsParseHandler] wrapping the matcher follow its group
+    /// boundaries. After parsing, it reflects the last group of the robots.txt.
+    pub fn in_group(&self) -> bool {
+        self.seen_any_agent()
+    }
+
+    /// Initialize next path and user-agents to check. Path must contain only the
+    /// path, params, and query (if any) of the url and must start with a '/'.
+    pub(crate) fn init_user_agents_and_path(
+        &mut self,
+        user_agents: Vec<&'a str>,
+        path: Cow<'a, str>,
+    ) {
+        self.path_rejected = false;
+        let path = match self.max_path_length {
+            Some((max_length, policy)) if path.len() > max_length => match policy {
+                LongPathPolicy::Truncate => {
+                    let mut end = max_length;
+                    while !path.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    match path {
+                        Cow::Borrowed(path) => Cow::Borrowed(&path[..end]),
+                        Cow::Owned(mut path) => {
+                            path.truncate(end);
+                            Cow::Owned(path)
+                        }
+                    }
+                }
+                LongPathPolicy::Reject => {
+                    self.path_rejected = true;
+                    Cow::Borrowed("")
+                }
+            },
+            _ => path,
+        };
+        // Only the path itself is collapsed, the query is left untouched.
+        let query_start = path.find('?').unwrap_or(path.len());
+        self.path = if self.collapse_slashes && path[..query_start].contains("//") {
+            let mut collapsed = String::with_capacity(path.len());
+            for ch in path[..query_start].chars() {
+                if !(ch == '/' && collapsed.ends_with('/')) {
+                    collapsed.push(ch);
+                }
+            }
+            collapsed.push_str(&path[query_start..]);
+            Cow::Owned(collapsed)
+        } else {
+            path
+        };
+        if self.decode_unreserved {
+            if let Cow::Owned(decoded) = decode_unreserved(&self.path) {
+                self.path = Cow::Owned(decoded);
+            }
+        }
+        if self.escaped_asterisks && self.path.contains('*') {
+            self.path = Cow::Owned(self.path.replace('*', "%2A"));
+        }
+        if self.sort_query_params {
+            if let Cow::Owned(sorted) = sort_query_params(&self.path) {
+                self.path = Cow::Owned(sorted);
+            }
+        }
+        self.user_agents = user_agents;
+    }
+
+    /// Returns true if 'url' is allowed to be fetched by any member of the
+    /// "user_agents" vector. 'url' must be %-encoded according to RFC3986.
+    pub fn allowed_by_robots(
+        &mut self,
+        robots_body: &'a str,
+        user_agents: Vec<&'a str>,
+        url: &'a str,
+    ) -> bool
+    where
+        Self: RobotsParseHandler,
+    {
+        // The url is not normalized (escaped, percent encoded) here because the user
+        // is asked to provide it in escaped form already.
+        let path = super::get_path_params_query(url);
+        self.allowed_for_path(robots_body, user_agents, path)
+    }
+
+    /// Same as [allowed_by_robots](Self::allowed_by_robots()), but gives up
+    /// with an error once the check took more than `budget` units of work,
+    /// so that the caller can choose a fallback instead of blocking on a huge
+    /// or pathological robots.txt.
+    ///
+    /// Parsing costs one unit per byte of the robots.txt, and matching a
+    /// pattern as many units as its length, times the length of the path plus
+    /// one if it has wildcards. Parsing stops soon after the budget runs out,
+    /// at most one line later.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    /// use robotstxt::matcher::BudgetExceeded;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /private\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert_eq!(
+    ///     Ok(false),
+    ///     matcher.allowed_by_robots_bounded(robots_body, vec!["FooBot"], "/private", 100)
+    /// );
+    /// assert_eq!(
+    ///     Err(BudgetExceeded),
+    ///     matcher.allowed_by_robots_bounded(robots_body, vec!["FooBot"], "/private", 20)
+    /// );
+    /// ```
+    pub fn allowed_by_robots_bounded(
+        &mut self,
+        robots_body: &'a str,
+        user_agents: Vec<&'a str>,
+        url: &'a str,
+        budget: usize,
+    ) -> Result<bool, BudgetExceeded>
+    where
+        Self: RobotsParseHandler,
+    {
+        self.work_budget = Some(budget);
+        let allowed = self.allowed_by_robots(robots_body, user_agents, url);
+        self.work_budget = None;
+        if self.budget_exceeded {
+            return Err(BudgetExceeded);
+        }
+        Ok(allowed)
+    }
+
+    /// Returns true if 'url' is allowed to be fetched by 'user_agent' both as it
+    /// is and in its canonical %-escaped form, as produced by [escape_pattern].
+    /// This covers rules written against either form when it's unclear whether
+    /// 'url' is already escaped.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /café\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/café"));
+    /// assert!(!matcher.allowed_considering_encodings(robots_body, "FooBot", "https://foo.com/café"));
+    /// assert!(!matcher.allowed_considering_encodings(robots_body, "FooBot", "https://foo.com/caf%C3%A9"));
+    /// ```
+    pub fn allowed_considering_encodings(
+        &mut self,
+        robots_body: &'a str,
+        user_agent: &'a str,
+        url: &'a str,
+    ) -> bool
+    where
+        Self: RobotsParseHandler,
+    {
+        let path = super::get_path_params_query(url);
+        let escaped_path = escape_pattern(&path);
+        if escaped_path != path
+            && !self.allowed_for_path(robots_body, vec![user_agent], Cow::Owned(escaped_path))
+        {
+            return false;
+        }
+        self.allowed_for_path(robots_body, vec![user_agent], path)
+    }
+
+    fn allowed_for_path(
+        &mut self,
+        robots_body: &'a str,
+        user_agents: Vec<&'a str>,
+        path: Cow<'a, str>,
+    ) -> bool
+    where
+        Self: RobotsParseHandler,
+    {
+        self.init_user_agents_and_path(user_agents, path);
+        self.parse(robots_body);
+        !self.disallow()
+    }
+
+    /// Same check as [one_agent_allowed_by_robots](Self::one_agent_allowed_by_robots),
+    /// but tells an explicit allow apart from the absence of any applying rule.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    /// use robotstxt::matcher::CrawlDecision;
+    ///
+    /// let mut matcher = DefaultMatcher::default();
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /\n\
+    ///                    allow: /fish\n";
+    /// assert_eq!(
+    ///     CrawlDecision::Allowed,
+    ///     matcher.crawl_decision(robots_body, "FooBot", "https://foo.com/fish")
+    /// );
+    /// assert_eq!(
+    ///     CrawlDecision::Disallowed,
+    ///     matcher.crawl_decision(robots_body, "FooBot", "https://foo.com/")
+    /// );
+    /// assert_eq!(
+    ///     CrawlDecision::NoRuleApplies,
+    ///     matcher.crawl_decision(robots_body, "BarBot", "https://foo.com/")
+    /// );
+    /// ```
+    pub fn crawl_decision(
+        &mut self,
+        robots_body: &'a str,
+        user_agent: &'a str,
+        url: &'a str,
+    ) -> CrawlDecision
+    where
+        Self: RobotsParseHandler,
+    {
+        let path = super::get_path_params_query(url);
+        self.init_user_agents_and_path(vec![user_agent], path);
+        self.parse(robots_body);
+        self.decision()
+    }
+
+    /// Parses the robots.txt, feeding this matcher.
+    fn parse(&mut self, robots_body: &str)
+    where
+        Self: RobotsParseHandler,
+    {
+        let google_extensions = !self.strict_rfc;
+        let strip_quotes = self.strip_quotes;
+        // Progress is what bounded checks take parsing work from.
+        let progress_interval = self.work_budget.map(|_| 1);
+        let mut parser = RobotsTxtParser::new(robots_body, self)
+            .with_google_extensions(google_extensions)
+            .with_quote_stripping(strip_quotes);
+        if let Some(progress_interval) = progress_interval {
+            parser = parser.with_progress_interval(progress_interval);
+        }
+        parser.parse();
+    }
+
+    /// Do robots check for 'url' when there is only one user agent. 'url' must
+    /// be %-encoded according to RFC3986.
+    pub fn one_agent_allowed_by_robots(
+        &mut self,
+        robots_txt: &'a str,
+        user_agent: &'a str,
+        url: &'a str,
+    ) -> bool
+    where
+        Self: RobotsParseHandler,
+    {
+        self.allowed_by_robots(robots_txt, vec![user_agent], url)
+    }
+
+    /// Returns true if we are disallowed from crawling a matching URI.
+    pub(crate) fn disallow(&self) -> bool {
+        self.decision() == CrawlDecision::Disallowed
+    }
+
+    /// Returns the decision for the current path, given the matches collected
+    /// while parsing.
+    fn decision(&self) -> CrawlDecision {
+        match self.decision_reason() {
+            DecisionReason::PathTooLong => CrawlDecision::Disallowed,
+            DecisionReason::WorkLimitExceeded => match self.work_limit_fallback {
+                WorkLimitFallback::Allow => CrawlDecision::Allowed,
+                WorkLimitFallback::Disallow => CrawlDecision::Disallowed,
+            },
+            DecisionReason::SpecificDisallow | DecisionReason::GlobalDisallow => {
+                CrawlDecision::Disallowed
+            }
+            DecisionReason::SpecificAllow | DecisionReason::GlobalAllow => CrawlDecision::Allowed,
+            DecisionReason::SpecificGroupEmpty | DecisionReason::NoMatchDefaultAllow => {
+                CrawlDecision::NoRuleApplies
+            }
+        }
+    }
+
+    /// Returns why the last check came out the way it did.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    /// use robotstxt::matcher::DecisionReason;
+    ///
+    /// let mut matcher = DefaultMatcher::default();
+    /// let robots_body = "user-agent: *\n\
+    ///                    disallow: /\n\
+    ///                    user-agent: FooBot\n\
+    ///                    allow: /fish\n";
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/fish"));
+    /// assert_eq!(DecisionReason::SpecificAllow, matcher.decision_reason());
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/cat"));
+    /// assert_eq!(DecisionReason::SpecificGroupEmpty, matcher.decision_reason());
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "BarBot", "https://foo.com/cat"));
+    /// assert_eq!(DecisionReason::GlobalDisallow, matcher.decision_reason());
+    /// ```
+    pub fn decision_reason(&self) -> DecisionReason {
+        if self.path_rejected {
+            return DecisionReason::PathTooLong;
+        }
+
+        if self.work_limit_line().is_some() {
+            return DecisionReason::WorkLimitExceeded;
+        }
+
+        if self.allow.specific.priority() > 0 || self.disallow.specific.priority() > 0 {
+            if self.disallow_wins(&self.disallow.specific, &self.allow.specific) {
+                return DecisionReason::SpecificDisallow;
+            }
+            return DecisionReason::SpecificAllow;
+        }
+
+        if self.ever_seen_specific_agent {
+            // Matching group for user-agent but either without disallow or empty one,
+            // i.e. priority == 0.
+            return DecisionReason::SpecificGroupEmpty;
+        }
+
+        if self.disallow.global.priority() > 0 || self.allow.global.priority() > 0 {
+            if self.disallow_wins(&self.disallow.global, &self.allow.global) {
+                return DecisionReason::GlobalDisallow;
+            }
+            return DecisionReason::GlobalAllow;
+        }
+
+        DecisionReason::NoMatchDefaultAllow
+    }
+
+    /// Returns true if an allow rule for `file_name` is normalized to allow the
+    /// directory too.
+    fn is_index_file(&self, file_name: &str) -> bool {
+        if self.skip_index_normalization {
+            return false;
+        }
+        match &self.index_files {
+            Some(index_files) => index_files
+                .iter()
+                .any(|index_file| file_name.starts_with(index_file.as_str())),
+            None => file_name.starts_with("index.htm"),
+        }
+    }
+
+    /// Returns true if any user-agent was seen.
+    fn seen_any_agent(&self) -> bool {
+        self.seen_global_agent || self.seen_specific_agent
+    }
+
+    /// Returns true if the user-agent value of a robots.txt record names the
+    /// global group.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// assert_eq!(true, DefaultMatcher::is_global_agent("*"));
+    /// assert_eq!(true, DefaultMatcher::is_global_agent("* "));
+    /// assert_eq!(true, DefaultMatcher::is_global_agent("* foo"));
+    /// assert_eq!(false, DefaultMatcher::is_global_agent("*foo"));
+    /// assert_eq!(false, DefaultMatcher::is_global_agent("foo"));
+    /// assert_eq!(false, DefaultMatcher::is_global_agent(""));
+    /// ```
+    pub fn is_global_agent(user_agent: &str) -> bool {
+        // Google-specific optimization: a '*' followed by space and more characters
+        // in a user-agent record is still regarded a global rule.
+        user_agent.starts_with('*')
+            && (user_agent.len() == 1 || user_agent[1..].starts_with(char::is_whitespace))
+    }
+
+    /// Extract the matchable part of a user agent string, essentially stopping at
+    /// the first invalid character.
+    /// Example: 'Googlebot/2.1' becomes 'Googlebot'
+    pub(crate) fn extract_user_agent(user_agent: &str) -> &str {
+        Self::extract_user_agent_parts(user_agent).0
+    }
+
+    /// Splits a user agent string into its matchable part, the one matched
+    /// against the user-agent lines of a robots.txt, and the discarded
+    /// rest, starting at the first character outside of [a-zA-Z_-]. A non-empty
+    /// rest in a robots.txt user-agent line is often a mistake of the author.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let f = DefaultMatcher::extract_user_agent_parts;
+    /// assert_eq!(("Googlebot", "/2.1"), f("Googlebot/2.1"));
+    /// assert_eq!(("Googlebot-Image", ""), f("Googlebot-Image"));
+    /// assert_eq!(("Foo", " Bar"), f("Foo Bar"));
+    /// assert_eq!(("", "*"), f("*"));
+    /// assert_eq!(("", ""), f(""));
+    /// ```
+    pub fn extract_user_agent_parts(user_agent: &str) -> (&str, &str) {
+        // Allowed characters in user-agent are [a-zA-Z_-].
+        let end = user_agent
+            .find(|c: char| !(c.is_ascii_alphabetic() || c == '-' || c == '_'))
+            .unwrap_or(user_agent.len());
+        user_agent.split_at(end)
+    }
+
+    /// Returns true if the rules of a group with user-agent line `robots_agent`
+    /// apply to `my_agent`: either the group is global, or the matchable parts of
+    /// both agents are equal, ignoring case.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// assert_eq!(true, DefaultMatcher::user_agent_matches("googlebot/2.1", "Googlebot"));
+    /// assert_eq!(true, DefaultMatcher::user_agent_matches("* # all", "Googlebot"));
+    /// assert_eq!(false, DefaultMatcher::user_agent_matches("Googlebot-Image", "Googlebot"));
+    /// ```
+    pub fn user_agent_matches(robots_agent: &str, my_agent: &str) -> bool {
+        Self::is_global_agent(robots_agent)
+            || Self::names_agent(robots_agent, Self::extract_user_agent(my_agent))
+    }
+
+    /// Returns true if the user-agent value of a robots.txt record names
+    /// `my_agent`, which must already be the matchable part of an agent.
+    fn names_agent(robots_agent: &str, my_agent: &str) -> bool {
+        Self::extract_user_agent(robots_agent).eq_ignore_ascii_case(my_agent)
+    }
+
+    /// Verifies that the given user agent is valid to be matched against
+    /// robots.txt. Valid user agent strings only contain the characters
+    /// [a-zA-Z_-].
+    pub fn is_valid_user_agent_to_obey(user_agent: &str) -> bool {
+        !user_agent.is_empty() && Self::extract_user_agent(user_agent) == user_agent
+    }
+
+    /// Returns true if we are disallowed from crawling a matching URI. Ignores any
+    /// rules specified for the default user agent, and bases its results only on
+    /// the specified user agents.
+    fn disallow_ignore_global(&self) -> bool {
+        if self.allow.specific.priority() > 0 || self.disallow.specific.priority() > 0 {
+            return self.disallow_wins(&self.disallow.specific, &self.allow.specific);
+        }
+        false
+    }
+
+    /// Returns true if the `disallow` match wins over the `allow` one,
+    /// according to the tie-break policy.
+    fn disallow_wins(&self, disallow: &Match, allow: &Match) -> bool {
+        match self.tie_break {
+            TieBreak::AllowWins => disallow.priority() > allow.priority(),
+            TieBreak::DisallowWins => disallow.priority() >= allow.priority(),
+        }
+    }
+
+    /// Returns the match priority of `pattern` against the current path,
+    /// or a negative value if it doesn't match.
+    fn match_priority(&mut self, kind: RuleKind, pattern: &str, line_num: u32) -> i32 {
+        let pattern = if self.decode_unreserved {
+            decode_unreserved(pattern)
+        } else {
+            Cow::Borrowed(pattern)
+        };
+        let pattern = if self.sort_query_params {
+            match sort_query_params(&pattern) {
+                Cow::Owned(sorted) => Cow::Owned(sorted),
+                Cow::Borrowed(_) => pattern,
+            }
+        } else {
+            pattern
+        };
+        let pattern = pattern.as_ref();
+        if self.path_rejected {
+            return Match::NO_MATCH_PRIORITY;
+        }
+        // Without wildcards, matching only takes the length of the pattern.
+        let has_wildcards = pattern.contains('*');
+        let work = if has_wildcards {
+            pattern.len().saturating_mul(self.path.len() + 1)
+        } else {
+            pattern.len()
+        };
+        if has_wildcards
+            && self
+                .match_work_limit
+                .is_some_and(|max_work| work > max_work)
+        {
+            let line = if self.seen_specific_agent {
+                &mut self.work_limit_lines.specific
+            } else {
+                &mut self.work_limit_lines.global
+            };
+            line.get_or_insert(line_num);
+            return Match::NO_MATCH_PRIORITY;
+        }
+        if !self.take_work(work) {
+            return Match::NO_MATCH_PRIORITY;
+        }
+        let priority = match kind {
+            RuleKind::Allow => self.match_strategy.match_allow(&self.path, pattern),
+            RuleKind::Disallow => self.match_strategy.match_disallow(&self.path, pattern),
+        };
+        if priority >= 0 && self.match_whole_segments && !pattern.ends_with(&['/', '*', '$'][..]) {
+            let on_boundary = ["$", "/", "?", ";"]
+                .iter()
+                .any(|boundary| S::matches(&self.path, &format!("{}{}", pattern, boundary)));
+            if !on_boundary {
+                return Match::NO_MATCH_PRIORITY;
+            }
+        }
+        priority
+    }
+
+    /// Takes `work` from the budget of a bounded check, returning false if
+    /// there isn't enough left.
+    fn take_work(&mut self, work: usize) -> bool {
+        if let Some(budget) = self.work_budget.as_mut() {
+            if self.budget_exceeded || work > *budget {
+                self.budget_exceeded = true;
+                return false;
+            }
+            *budget -= work;
+        }
+        true
+    }
+
+    fn record_match(
+        &mut self,
+        kind: RuleKind,
+        pattern: &str,
+        priority: i32,
+        line: u32,
+        index_normalized: bool,
+    ) {
+        if self.record_matches {
+            self.matched_rules.push(MatchedRule {
+                kind,
+                pattern: pattern.to_string(),
+                priority,
+                line,
+                specific: self.seen_specific_agent,
+                index_normalized,
+            });
+        }
+    }
+
+    fn log_step(&mut self, kind: RuleKind, pattern: &str, priority: i32, line: u32) {
+        if self.log_evaluation {
+            let (best_allow, best_disallow) = if self.seen_specific_agent {
+                (&self.allow.specific, &self.disallow.specific)
+            } else {
+                (&self.allow.global, &self.disallow.global)
+            };
+            let step = EvalStep {
+                kind,
+                pattern: pattern.to_string(),
+                line,
+                specific: self.seen_specific_agent,
+                priority,
+                best_allow: best_allow.priority(),
+                best_disallow: best_disallow.priority(),
+            };
+            self.evaluation_log.push(step);
+        }
+    }
+
+    /// Returns the line that matched or 0 if none matched. If the check was
+    /// decided by [DecisionReason::WorkLimitExceeded], this is the line of the
+    /// pattern which went over the limit. A path rejected for its length
+    /// matches no rule, see [DecisionReason::PathTooLong].
+    pub fn matching_line(&self) -> u32 {
+        if let Some(line) = self.work_limit_line() {
+            return line;
+        }
+        self.deciding_match().line()
+    }
+
+    /// Returns true if the last check was decided by an 'Allow: .../index.html'
+    /// line matching once normalized to its directory, see
+    /// [with_index_normalization](Self::with_index_normalization()). The
+    /// [matching_line](Self::matching_line()) is then the line of the original
+    /// Allow rule.
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /\n\
+    ///                    allow: /index.html\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/"));
+    /// assert!(matcher.matched_via_index_normalization());
+    /// assert_eq!(3, matcher.matching_line());
+    ///
+    /// assert!(matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/index.html"));
+    /// assert!(!matcher.matched_via_index_normalization());
+    /// ```
+    pub fn matched_via_index_normalization(&self) -> bool {
+        self.work_limit_line().is_none() && self.deciding_match().index_normalized()
+    }
+
+    /// Returns the best match of the groups obeyed, the one deciding the check.
+    fn deciding_match(&self) -> &Match {
+        let hierarchy = |hierarchy: fn(&MatchHierarchy) -> &Match| {
+            let disallow = hierarchy(&self.disallow);
+            let allow = hierarchy(&self.allow);
+            if self.tie_break == TieBreak::DisallowWins && disallow.priority() == allow.priority() {
+                return disallow;
+            }
+            Match::higher_priority_match(disallow, allow)
+        };
+        if self.ever_seen_specific_agent {
+            return hierarchy(|hierarchy| &hierarchy.specific);
+        }
+        hierarchy(|hierarchy| &hierarchy.global)
+    }
+
+    /// Explains the outcome of the last check in a sentence, built from its
+    /// [decision_reason](Self::decision_reason()) and
+    /// [matching_line](Self::matching_line()). The pattern of the deciding
+    /// rule is only known with [with_match_recording](Self::with_match_recording()).
+    /// ```rust
+    /// use robotstxt::DefaultMatcher;
+    ///
+    /// let robots_body = "user-agent: *\n\
+    ///                    disallow: /\n\
+    ///                    \n\
+    ///                    user-agent: FooBot\n\
+    ///                    disallow: /private\n";
+    /// let mut matcher = DefaultMatcher::default().with_match_recording(true);
+    /// matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/private");
+    /// assert_eq!(
+    ///     "Disallowed by 'Disallow: /private' (line 5) in the FooBot group.",
+    ///     matcher.explain()
+    /// );
+    /// matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/public");
+    /// assert_eq!("Allowed, as no rule of the FooBot group matches.", matcher.explain());
+    /// matcher.one_agent_allowed_by_robots(robots_body, "BarBot", "https://foo.com/public");
+    /// assert_eq!(
+    ///     "Disallowed by 'Disallow: /' (line 2) in the global group, as no group names BarBot.",
+    ///     matcher.explain()
+    /// );
+    ///
+    /// let mut matcher = DefaultMatcher::default();
+    /// matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "https://foo.com/private");
+    /// assert_eq!(
+    ///     "Disallowed by the rule on line 5 in the FooBot group.",
+    ///     matcher.explain()
+    /// );
+    /// ```
+    pub fn explain(&self) -> String {
+        let agents = self.user_agents.join(" or ");
+        let reason = self.decision_reason();
+        let (verdict, kind, specific) = match reason {
+            DecisionReason::SpecificDisallow => ("Disallowed", RuleKind::Disallow, true),
+            DecisionReason::SpecificAllow => ("Allowed", RuleKind::Allow, true),
+            DecisionReason::GlobalDisallow => ("Disallowed", RuleKind::Disallow, false),
+            DecisionReason::GlobalAllow => ("Allowed", RuleKind::Allow, false),
+            DecisionReason::SpecificGroupEmpty => {
+                return format!("Allowed, as no rule of the {} group matches.", agents);
+            }
+            DecisionReason::NoMatchDefaultAllow => {
+                return format!(
+                    "Allowed, as no group names {} and no global rule matches.",
+                    agents
+                );
+            }
+            DecisionReason::PathTooLong => {
+                let max_length = self.max_path_length.map_or(0, |(max_length, _)| max_length);
+                return format!(
+                    "Disallowed, as the path is longer than the maximum of {} bytes.",
+                    max_length
+                );
+            }
+            DecisionReason::WorkLimitExceeded => {
+                let verdict = match self.work_limit_fallback {
+                    WorkLimitFallback::Allow => "Allowed",
+                    WorkLimitFallback::Disallow => "Disallowed",
+                };
+                return format!(
+                    "{}, as the pattern on line {} needs more matching work than allowed.",
+                    verdict,
+                    self.matching_line()
+                );
+            }
+        };
+
+        let line = self.matching_line();
+        let rule = match self
+            .matched_rules
+            .iter()
+            .find(|rule| rule.kind == kind && rule.line == line && rule.specific == specific)
+        {
+            Some(rule) => {
+                let key = match kind {
+                    RuleKind::Allow => "Allow",
+                    RuleKind::Disallow => "Disallow",
+                };
+                format!("'{}: {}' (line {})", key, rule.pattern, line)
+            }
+            None => format!("the rule on line {}", line),
+        };
+        if specific {
+            format!("{} by {} in the {} group.", verdict, rule, agents)
+        } else {
+            format!(
+                "{} by {} in the global group, as no group names {}.",
+                verdict, rule, agents
+            )
+        }
+    }
+
+    /// Clears the state of the last check: the matches, the groups seen and the
+    /// logs. This is done at the start of every robots.txt parse, and only
+    /// needed to drive the matcher by hand, calling its [RobotsParseHandler]
+    /// methods with directives from another source. The user-agents and path
+    /// of the last check and the options are kept.
+    /// ```rust
+    /// use robotstxt::{DefaultMatcher, RobotsParseHandler};
+    /// use robotstxt::matcher::DecisionReason;
+    ///
+    /// let robots_body = "user-agent: FooBot\n\
+    ///                    disallow: /private\n";
+    /// let mut matcher = DefaultMatcher::default();
+    /// assert!(!matcher.one_agent_allowed_by_robots(robots_body, "FooBot", "/private"));
+    /// assert_eq!(2, matcher.matching_line());
+    ///
+    /// matcher.reset();
+    /// assert_eq!(0, matcher.matching_line());
+    /// // Check the same agent and path against other directives.
+    /// matcher.handle_user_agent(1, "FooBot");
+    /// matcher.handle_allow(2, "/private");
+    /// assert_eq!(DecisionReason::SpecificAllow, matcher.decision_reason());
+    /// ```
+    pub fn reset(&mut self) {
+        // We do it in the same order the instance member variables are declared,
+        // so it's easier to keep track of which ones we have (or maybe haven't!)
+        // done.
+        self.allow.clear();
+        self.disallow.clear();
+
+        self.seen_global_agent = false;
+        self.seen_specific_agent = false;
+        self.ever_seen_specific_agent = false;
+        self.specific_agent_len = 0;
+        self.seen_separator = false;
+        self.specific_group_done = false;
+        self.matched_rules.clear();
+        self.evaluation_log.clear();
+        self.work_limit_lines = WorkLimitLines::default();
+        self.budget_parsed_bytes = 0;
+        self.budget_exceeded = false;
+    }
+
+    /// Handles one user-agent of a user-agent line.
+    fn handle_agent_token(&mut self, user_agent: &str) {
+        let is_global_agent = if self.strict_rfc {
+            user_agent == "*"
+        } else {
+            Self::is_global_agent(user_agent)
+        };
+        if is_global_agent {
+            self.seen_global_agent = true;
+        } else {
+            if self
+                .user_agents
+                .iter()
+                .any(|agent| Self::names_agent(user_agent, agent))
+            {
+                let user_agent = Self::extract_user_agent(user_agent);
+                if self.select_longest_agent {
+                    if user_agent.len() < self.specific_agent_len {
+                        return;
+                    }
+                    if user_agent.len() > self.specific_agent_len {
+                        // The groups seen so far are less specific, forget their rules.
+                        self.allow.specific.clear();
+                        self.disallow.specific.clear();
+                        self.matched_rules.retain(|rule| !rule.specific);
+                        self.work_limit_lines.specific = None;
+                        self.specific_agent_len = user_agent.len();
+                    }
+                }
+                self.ever_seen_specific_agent = true;
+                self.seen_specific_agent = true;
+            }
+        }
+    }
+
+    /// Handles an allow rule, `index_normalized` telling whether `value` is the
+    /// normalized pattern of an 'Allow: .../index.html' line.
+    fn handle_allow_pattern(&mut self, line_num: u32, value: &str, index_normalized: bool) {
+        if !self.seen_any_agent() {
+            return;
+        }
+
+        self.seen_separator = true;
+        if self.ignore_allow_rules {
+            return;
+        }
+        let priority = self.match_priority(RuleKind::Allow, value, line_num);
+        if priority >= 0 {
+            self.record_match(RuleKind::Allow, value, priority, line_num, index_normalized);
+            let best = if self.seen_specific_agent {
+                &mut self.allow.specific
+            } else {
+                &mut self.allow.global
+            };
+            if best.priority() < priority {
+                if index_normalized {
+                    best.set_index_normalized(priority, line_num);
+                } else {
+                    best.set(priority, line_num);
+                }
+            }
+        }
+        self.log_step(RuleKind::Allow, value, priority, line_num);
+        if priority < 0 {
+            // Google-specific optimization: 'index.htm' and 'index.html' are normalized to '/'.
+            // The index files may be configured, see 'with_index_files'.
+            let slash_pos = value.rfind('/');
+
+            if let Some(slash_pos) = slash_pos {
+                if self.is_index_file(&value[slash_pos + 1..]) {
+                    let new_pattern = format!("{}{}", &value[..(slash_pos + 1)], "$");
+                    self.handle_allow_pattern(line_num, &new_pattern, true);
+                }
+            }
+        }
+    }
+}
+
+impl<S: RobotsMatchStrategy> RobotsParseHandler for RobotsMatcher<'_, S> {
+    fn handle_robots_start(&mut self) {
+        // This is a new robots.txt file, so we need to reset all the instance member
+        // variables.
+        self.reset();
+    }
+
+    fn handle_robots_end(&mut self) {
+        // The global groups are ignored once a group names one of our agents.
+        if self.ever_seen_specific_agent {
+            self.matched_rules.retain(|rule| rule.specific);
+        }
+    }
+
+    fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+        if self.seen_separator {
+            if self.stop_after_specific_group && self.seen_specific_agent {
+                self.specific_group_done = true;
+                return;
+            }
+            self.seen_specific_agent = false;
+            self.seen_global_agent = false;
+            self.seen_separator = false;
+        }
+
+        if self.split_agent_lists && user_agent.contains(',') {
+            for user_agent in user_agent.split(',').map(str::trim) {
+                if !user_agent.is_empty() {
+                    self.handle_agent_token(user_agent);
+                }
+            }
+        } else {
+            self.handle_agent_token(user_agent);
+        }
+    }
+
+    fn handle_allow(&mut self, line_num: u32, value: &str) {
+        self.handle_allow_pattern(line_num, value, false);
+    }
+
+    fn handle_disallow(&mut self, line_num: u32, value: &str) {
         if !self.seen_any_agent() {
             return;
         }
 
         self.seen_separator = true;
-        let priority = self.match_strategy.match_disallow(&self.path, value);
+        let priority = self.match_priority(RuleKind::Disallow, value, line_num);
         if priority >= 0 {
+            self.record_match(RuleKind::Disallow, value, priority, line_num, false);
             if self.seen_specific_agent {
                 if self.disallow.specific.priority() < priority {
                     self.disallow.specific.set(priority, line_num);
@@ -434,6 +1961,7 @@ impl<S: RobotsMatchStrategy> RobotsParseHandler for RobotsMatcher<'_, S> {
                 self.disallow.global.set(priority, line_num);
             }
         }
+        self.log_step(RuleKind::Disallow, value, priority, line_num);
     }
 
     fn handle_sitemap(&mut self, line_num: u32, value: &str) {
@@ -443,14 +1971,49 @@ impl<S: RobotsMatchStrategy> RobotsParseHandler for RobotsMatcher<'_, S> {
     fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {
         self.seen_separator = true;
     }
+
+    fn handle_progress(&mut self, bytes_processed: usize, _total_bytes: usize) {
+        let parsed = bytes_processed.saturating_sub(self.budget_parsed_bytes);
+        self.budget_parsed_bytes = bytes_processed;
+        self.take_work(parsed);
+    }
+
+    fn stop_parsing(&self) -> bool {
+        self.specific_group_done || self.budget_exceeded
+    }
+}
+
+/// Returns `path` with the parameters of its query sorted, keeping a trailing
+/// '$' at the end. The original string is returned if they are sorted already.
+fn sort_query_params(path: &str) -> Cow<'_, str> {
+    let query_start = match path.find('?') {
+        Some(pos) => pos + 1,
+        None => return Cow::Borrowed(path),
+    };
+    let (query, anchor) = match path[query_start..].strip_suffix('$') {
+        Some(query) => (query, "$"),
+        None => (&path[query_start..], ""),
+    };
+    let mut params: Vec<&str> = query.split('&').collect();
+    if params.windows(2).all(|pair| pair[0] <= pair[1]) {
+        return Cow::Borrowed(path);
+    }
+    params.sort_unstable();
+    Cow::Owned(format!(
+        "{}{}{}",
+        &path[..query_start],
+        params.join("&"),
+        anchor
+    ))
 }
 
 #[cfg(test)]
 mod test {
     use crate::matcher::*;
+    use crate::DefaultMatcher;
 
     #[test]
-    fn test_extract_user_agent<'a>() {
+    fn test_extract_user_agent() {
         // Example: 'Googlebot/2.1' becomes 'Googlebot'
         type Target<'a> = RobotsMatcher<'a, LongestMatchRobotsMatchStrategy>;
         assert_eq!("Googlebot", Target::extract_user_agent("Googlebot/2.1"));
@@ -461,4 +2024,1142 @@ mod test {
         assert_eq!("", Target::extract_user_agent("1Googlebot_2.1"));
         assert_eq!("Goo", Target::extract_user_agent("Goo1glebot_2.1"));
     }
+
+    #[test]
+    fn test_user_agent_matches() {
+        type Target<'a> = RobotsMatcher<'a, LongestMatchRobotsMatchStrategy>;
+        assert!(Target::user_agent_matches("Googlebot/2.1", "Googlebot"));
+        assert!(Target::user_agent_matches("Googlebot", "googlebot/2.1"));
+        assert!(Target::user_agent_matches("Googlebot-", "Googlebot-"));
+        assert!(Target::user_agent_matches("Googlebot_2.1", "Googlebot_"));
+        assert!(Target::user_agent_matches("Goo1glebot_2.1", "Goo"));
+        assert!(Target::user_agent_matches("*", "Googlebot"));
+        assert!(Target::user_agent_matches("* Googlebot", "Bingbot"));
+        assert!(!Target::user_agent_matches("*Googlebot", "Googlebot"));
+        assert!(!Target::user_agent_matches("Googlebot-Image", "Googlebot"));
+        assert!(!Target::user_agent_matches("1Googlebot_2.1", "Googlebot"));
+    }
+
+    #[test]
+    fn test_matches_after_wildcard() {
+        type Target = LongestMatchRobotsMatchStrategy;
+        assert!(Target::matches("/aab", "/*ab"));
+        assert!(Target::matches("/abcabd", "/*abd"));
+        assert!(Target::matches("/a/b/a/b/c", "/*/b/c$"));
+        assert!(!Target::matches("/a/b/a/b/c", "/*/b/d"));
+        assert!(Target::matches("/SanJos%C3%A9", "/*%C3%A9$"));
+    }
+
+    #[test]
+    fn test_plus_is_literal() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /search?q=a+b\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/search?q=a+b"
+        ));
+        assert!(matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/search?q=a%20b"
+        ));
+        assert!(matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/search?q=a b"
+        ));
+    }
+
+    #[test]
+    fn test_consecutive_wildcards() {
+        type Target = LongestMatchRobotsMatchStrategy;
+        for path in &["/", "/a", "/a/b/c", "/a/b/c.html", "/x/a/b"] {
+            for (pattern, coalesced) in &[
+                ("/**", "/*"),
+                ("/***", "/*"),
+                ("/a/**/c", "/a/*/c"),
+                ("/**.html$", "/*.html$"),
+                ("**/b", "*/b"),
+            ] {
+                assert_eq!(
+                    Target::matches(path, coalesced),
+                    Target::matches(path, pattern),
+                    "{} {}",
+                    path,
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_longest_agent_selection() {
+        let robotstxt = "user-agent: Bot\n\
+        disallow: /\n\
+        allow: /public\n\
+        \n\
+        user-agent: BotX\n\
+        disallow: /private\n\
+        \n\
+        user-agent: Bot\n\
+        disallow: /tmp\n";
+        let agents = vec!["Bot", "BotX"];
+
+        let mut matcher = DefaultMatcher::default();
+        assert!(!matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/a"));
+        assert!(!matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/private"));
+        assert!(matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/public"));
+
+        let mut matcher = DefaultMatcher::default().with_longest_agent_selection(true);
+        assert!(matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/a"));
+        assert!(matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/tmp"));
+        assert!(!matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/private"));
+        // Only the "Bot" groups apply if that's the only agent we check.
+        assert!(!matcher.allowed_by_robots(robotstxt, vec!["Bot"], "http://foo.com/tmp"));
+        assert!(matcher.allowed_by_robots(robotstxt, vec!["Bot"], "http://foo.com/public"));
+
+        // A group naming both agents is governed by the longest one.
+        let robotstxt = "user-agent: Bot\n\
+        user-agent: BotX\n\
+        disallow: /private\n\
+        \n\
+        user-agent: Bot\n\
+        disallow: /\n";
+        assert!(matcher.allowed_by_robots(robotstxt, agents.clone(), "http://foo.com/a"));
+        assert!(!matcher.allowed_by_robots(robotstxt, agents, "http://foo.com/private"));
+    }
+
+    #[test]
+    fn test_matching_rules() {
+        let robotstxt = "user-agent: *\n\
+        disallow: /a\n\
+        \n\
+        user-agent: FooBot\n\
+        user-agent: *\n\
+        allow: /a/b\n\
+        disallow: /c\n\
+        allow: /a/index.html\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/a/"));
+        assert!(matcher.matching_rules().is_empty());
+
+        let mut matcher = DefaultMatcher::default().with_match_recording(true);
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/a/"));
+        let rules = matcher.matching_rules();
+        assert_eq!(2, rules.len());
+        assert_eq!(
+            MatchedRule {
+                kind: RuleKind::Disallow,
+                pattern: "/a".into(),
+                priority: 2,
+                line: 2,
+                specific: false,
+                index_normalized: false
+            },
+            rules[0]
+        );
+        assert_eq!(
+            MatchedRule {
+                kind: RuleKind::Allow,
+                pattern: "/a/$".into(),
+                priority: 4,
+                line: 8,
+                specific: false,
+                index_normalized: true
+            },
+            rules[1]
+        );
+
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/c"));
+        assert_eq!(1, matcher.matching_rules().len());
+        assert!(matcher.matching_rules()[0].specific);
+
+        // The global 'disallow: /a' matches too, but FooBot ignores it.
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/a/b"));
+        let lines: Vec<_> = matcher
+            .matching_rules()
+            .iter()
+            .map(|rule| rule.line)
+            .collect();
+        assert_eq!(vec![6], lines);
+    }
+
+    #[test]
+    fn test_stop_after_specific_group() {
+        let robotstxt = "user-agent: *\n\
+        disallow: /global\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /a\n\
+        \n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /b\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/b"));
+
+        let mut matcher = DefaultMatcher::default().with_stop_after_specific_group(true);
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/a"));
+        // The second FooBot group is never seen.
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/b"));
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/global"));
+        // Agents without a group of their own still read the whole file.
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "BazBot", "http://foo.com/global"));
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/b"));
+    }
+
+    #[test]
+    fn test_allowed_considering_encodings() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /caf%c3%a9\n";
+        let mut matcher = DefaultMatcher::default();
+        let url = "http://foo.com/caf%c3%a9";
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url));
+        assert!(!matcher.allowed_considering_encodings(robotstxt, "FooBot", url));
+        assert!(matcher.allowed_considering_encodings(robotstxt, "FooBot", "http://foo.com/cafe"));
+    }
+
+    #[test]
+    fn test_whole_segment_matching() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /dir\n\
+        disallow: /*.gif$\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/dir"));
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/directory"
+        ));
+
+        let mut matcher = DefaultMatcher::default().with_whole_segment_matching(true);
+        for url in &[
+            "http://foo.com/dir",
+            "http://foo.com/dir/",
+            "http://foo.com/dir/page.html",
+            "http://foo.com/dir?a=b",
+            "http://foo.com/dir;a",
+            "http://foo.com/a.gif",
+        ] {
+            assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url));
+        }
+        assert!(matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/directory"
+        ));
+        assert!(matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/dir.html"
+        ));
+    }
+
+    #[test]
+    fn test_crawl_decision() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow:\n\
+        \n\
+        user-agent: *\n\
+        allow: /public\n\
+        disallow: /\n";
+        let mut matcher = DefaultMatcher::default();
+        let decision = |matcher: &mut DefaultMatcher, agent, url| {
+            let decision = matcher.crawl_decision(robotstxt, agent, url);
+            assert_eq!(
+                decision != CrawlDecision::Disallowed,
+                matcher.one_agent_allowed_by_robots(robotstxt, agent, url)
+            );
+            decision
+        };
+        // FooBot has its own group, which has only an empty disallow.
+        assert_eq!(
+            CrawlDecision::NoRuleApplies,
+            decision(&mut matcher, "FooBot", "http://foo.com/")
+        );
+        assert_eq!(
+            CrawlDecision::Allowed,
+            decision(&mut matcher, "BarBot", "http://foo.com/public")
+        );
+        assert_eq!(
+            CrawlDecision::Disallowed,
+            decision(&mut matcher, "BarBot", "http://foo.com/private")
+        );
+        assert_eq!(
+            CrawlDecision::NoRuleApplies,
+            matcher.crawl_decision("", "FooBot", "http://foo.com/")
+        );
+    }
+
+    #[test]
+    fn test_evaluation_log() {
+        let robotstxt = "user-agent: *\n\
+        disallow: /\n\
+        user-agent: FooBot\n\
+        allow: /a/index.html\n\
+        disallow: /a\n\
+        user-agent: BarBot\n\
+        disallow: /\n";
+        let mut matcher = DefaultMatcher::default().with_evaluation_log(true);
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/a/"));
+        let steps = matcher.take_evaluation_log();
+        let expected = vec![
+            (RuleKind::Disallow, "/", 2, false, 1, -1, 1),
+            // The index.html allow is retried normalized to its directory.
+            (RuleKind::Allow, "/a/index.html", 4, true, -1, -1, -1),
+            (RuleKind::Allow, "/a/$", 4, true, 4, 4, -1),
+            (RuleKind::Disallow, "/a", 5, true, 2, 4, 2),
+        ];
+        let actual: Vec<_> = steps
+            .iter()
+            .map(|step| {
+                (
+                    step.kind,
+                    step.pattern.as_str(),
+                    step.line,
+                    step.specific,
+                    step.priority,
+                    step.best_allow,
+                    step.best_disallow,
+                )
+            })
+            .collect();
+        assert_eq!(expected, actual);
+
+        let mut matcher = DefaultMatcher::default();
+        matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/a/");
+        assert!(matcher.take_evaluation_log().is_empty());
+    }
+
+    #[test]
+    fn test_decision_reason() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /a\n\
+        allow: /a/b\n\
+        \n\
+        user-agent: *\n\
+        disallow: /c\n\
+        allow: /c/d\n";
+        let mut matcher = DefaultMatcher::default();
+        for (agent, url, allowed, reason) in &[
+            (
+                "FooBot",
+                "http://foo.com/a",
+                false,
+                DecisionReason::SpecificDisallow,
+            ),
+            (
+                "FooBot",
+                "http://foo.com/a/b",
+                true,
+                DecisionReason::SpecificAllow,
+            ),
+            (
+                "FooBot",
+                "http://foo.com/c",
+                true,
+                DecisionReason::SpecificGroupEmpty,
+            ),
+            (
+                "BarBot",
+                "http://foo.com/c",
+                false,
+                DecisionReason::GlobalDisallow,
+            ),
+            (
+                "BarBot",
+                "http://foo.com/c/d",
+                true,
+                DecisionReason::GlobalAllow,
+            ),
+            (
+                "BarBot",
+                "http://foo.com/a",
+                true,
+                DecisionReason::NoMatchDefaultAllow,
+            ),
+        ] {
+            assert_eq!(
+                *allowed,
+                matcher.one_agent_allowed_by_robots(robotstxt, agent, url)
+            );
+            assert_eq!(*reason, matcher.decision_reason(), "{} {}", agent, url);
+        }
+    }
+
+    #[test]
+    fn test_slash_collapsing() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /a/b\n\
+        disallow: /c//d\n\
+        disallow: /e?x=//\n";
+        let url = "http://example.com//a///b";
+        let mut matcher = DefaultMatcher::default();
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url));
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://example.com/c//d"
+        ));
+
+        let mut matcher = DefaultMatcher::default().with_slash_collapsing(true);
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url));
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://example.com//e?x=//"
+        ));
+        // Patterns aren't collapsed, so this one can't match anymore.
+        assert!(matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://example.com/c//d"
+        ));
+    }
+
+    #[test]
+    fn test_unreserved_decoding() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /private\n\
+        disallow: /%7Euser\n\
+        disallow: /a%2Fb\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/%70rivate"
+        ));
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/~user"));
+
+        let mut matcher = DefaultMatcher::default().with_unreserved_decoding(true);
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/%70rivate"
+        ));
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/%70%72ivate/x"
+        ));
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/~user"));
+        // Reserved characters stay encoded.
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/a/b"));
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/a%2Fb"));
+    }
+
+    #[test]
+    fn test_in_group() {
+        struct Wrapper<'a> {
+            matcher: DefaultMatcher<'a>,
+            in_group: Vec<(u32, bool)>,
+        }
+
+        impl RobotsParseHandler for Wrapper<'_> {
+            fn handle_robots_start(&mut self) {
+                self.matcher.handle_robots_start();
+            }
+            fn handle_robots_end(&mut self) {
+                self.matcher.handle_robots_end();
+            }
+            fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+                self.matcher.handle_user_agent(line_num, user_agent);
+                self.in_group.push((line_num, self.matcher.in_group()));
+            }
+            fn handle_allow(&mut self, line_num: u32, value: &str) {
+                self.matcher.handle_allow(line_num, value);
+                self.in_group.push((line_num, self.matcher.in_group()));
+            }
+            fn handle_disallow(&mut self, line_num: u32, value: &str) {
+                self.matcher.handle_disallow(line_num, value);
+                self.in_group.push((line_num, self.matcher.in_group()));
+            }
+            fn handle_sitemap(&mut self, line_num: u32, value: &str) {
+                self.matcher.handle_sitemap(line_num, value);
+            }
+            fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {
+                self.matcher.handle_unknown_action(line_num, action, value);
+            }
+        }
+
+        let robotstxt = "disallow: /\n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        user-agent: FooBot\n\
+        disallow: /a\n\
+        user-agent: *\n\
+        allow: /b\n";
+        let mut matcher = DefaultMatcher::default();
+        matcher.init_user_agents_and_path(vec!["FooBot"], Cow::Borrowed("/a"));
+        let mut wrapper = Wrapper {
+            matcher,
+            in_group: vec![],
+        };
+        crate::parse_robotstxt(robotstxt, &mut wrapper);
+        assert_eq!(
+            vec![
+                (1, false),
+                (2, false),
+                (3, false),
+                (4, true),
+                (5, true),
+                (6, true),
+                (7, true)
+            ],
+            wrapper.in_group
+        );
+        assert!(wrapper.matcher.disallow());
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let robotstxt = "user-agent: FooBot\n\
+        allow: /x\n\
+        disallow: /x\n\
+        \n\
+        user-agent: *\n\
+        disallow: /y\n\
+        allow: /y\n\
+        disallow: /z$\n\
+        allow: /z\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/x"));
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/y"));
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/z"));
+
+        let mut matcher = DefaultMatcher::default().with_tie_break(TieBreak::DisallowWins);
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/x"));
+        assert_eq!(DecisionReason::SpecificDisallow, matcher.decision_reason());
+        assert_eq!(3, matcher.matching_line());
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/y"));
+        assert_eq!(DecisionReason::GlobalDisallow, matcher.decision_reason());
+        assert_eq!(6, matcher.matching_line());
+        // No tie here, the longer disallow rule wins either way.
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/z"));
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", "http://foo.com/zz"));
+    }
+
+    #[test]
+    fn test_strict_rfc() {
+        let robotstxt = "\u{FEFF}user-agent: FooBot\n\
+        disallow: /\n\
+        allow: /dir/index.html\n\
+        \n\
+        user-agent: * BarBot\n\
+        disallow: /\n";
+        let check_body = |robotstxt: &str, strict: bool, user_agent: &str, url: &str| {
+            let mut matcher = if strict {
+                DefaultMatcher::strict_rfc()
+            } else {
+                DefaultMatcher::default()
+            };
+            matcher.one_agent_allowed_by_robots(robotstxt, user_agent, url)
+        };
+        let check = |strict: bool, user_agent: &str, url: &str| {
+            check_body(robotstxt, strict, user_agent, url)
+        };
+        assert!(!check(false, "FooBot", "http://foo.com/a"));
+        assert!(check(false, "FooBot", "http://foo.com/dir/"));
+        assert!(!check(false, "BazBot", "http://foo.com/a"));
+
+        // The byte order mark makes the first key unknown, so FooBot's rules
+        // are outside of any group.
+        assert!(check(true, "FooBot", "http://foo.com/a"));
+        // `* BarBot` isn't global, and doesn't name BarBot either.
+        assert!(check(true, "BazBot", "http://foo.com/a"));
+        assert!(check(true, "BarBot", "http://foo.com/a"));
+
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /\n\
+        allow: /dir/index.html\n";
+        assert!(check_body(
+            robotstxt,
+            false,
+            "FooBot",
+            "http://foo.com/dir/"
+        ));
+        assert!(check_body(
+            robotstxt,
+            true,
+            "FooBot",
+            "http://foo.com/dir/index.html"
+        ));
+        assert!(!check_body(
+            robotstxt,
+            true,
+            "FooBot",
+            "http://foo.com/dir/"
+        ));
+    }
+
+    #[test]
+    fn test_trailing_wildcard() {
+        type Target = LongestMatchRobotsMatchStrategy;
+        for path in &["/private", "/privatex", "/private/anything", "/private?a=b"] {
+            assert!(Target::matches(path, "/private*"), "{}", path);
+            assert_eq!(
+                Target::matches(path, "/private"),
+                Target::matches(path, "/private*")
+            );
+        }
+        assert!(!Target::matches("/privat", "/private*"));
+        assert!(!Target::matches("/Private", "/private*"));
+
+        // The trailing '*' counts in the length of the match, and so wins over
+        // the same pattern without it.
+        let robotstxt = "user-agent: FooBot\n\
+        allow: /private\n\
+        disallow: /private*\n";
+        let mut matcher = DefaultMatcher::default();
+        assert!(!matcher.one_agent_allowed_by_robots(
+            robotstxt,
+            "FooBot",
+            "http://foo.com/private"
+        ));
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "http://foo.com/"));
+    }
+
+    #[test]
+    fn test_closure_match_strategy() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /\n\
+        allow: /public\n";
+        let url = "http://foo.com/public";
+
+        let mut matcher = RobotsMatcher::<ClosureMatchStrategy>::default();
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url));
+
+        // Disallow rules always win.
+        let mut matcher = RobotsMatcher::default().with_match_strategy(ClosureMatchStrategy::new(
+            |path, pattern| {
+                if ClosureMatchStrategy::matches(path, pattern) {
+                    0
+                } else {
+                    -1
+                }
+            },
+            |path, pattern| {
+                if ClosureMatchStrategy::matches(path, pattern) {
+                    i32::MAX
+                } else {
+                    -1
+                }
+            },
+        ));
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url));
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", url));
+
+        // The matcher can be moved to another thread.
+        let allowed = std::thread::spawn(move || {
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        });
+        assert!(!allowed.join().unwrap());
+    }
+
+    #[test]
+    fn test_index_normalization() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /\n\
+        allow: /dir/index.html\n\
+        allow: /other/index.htm\n\
+        allow: /php/index.php\n";
+        let check = |matcher: DefaultMatcher, url: &str| {
+            let mut matcher = matcher;
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        };
+
+        for url in &["http://foo.com/dir/", "http://foo.com/other/"] {
+            assert!(check(DefaultMatcher::default(), url));
+            assert!(check(
+                DefaultMatcher::default().with_index_normalization(true),
+                url
+            ));
+            assert!(!check(
+                DefaultMatcher::default().with_index_normalization(false),
+                url
+            ));
+        }
+        // Only the directory itself is allowed.
+        assert!(!check(DefaultMatcher::default(), "http://foo.com/dir/a"));
+        assert!(!check(DefaultMatcher::default(), "http://foo.com/php/"));
+        // The index files themselves are allowed either way.
+        assert!(check(
+            DefaultMatcher::default().with_index_normalization(false),
+            "http://foo.com/dir/index.html"
+        ));
+
+        let matcher = || DefaultMatcher::default().with_index_files(&["index.php", ""]);
+        assert!(check(matcher(), "http://foo.com/php/"));
+        assert!(!check(matcher(), "http://foo.com/dir/"));
+        assert!(!check(matcher(), "http://foo.com/"));
+        // Strict matching can have it back.
+        assert!(check(
+            DefaultMatcher::strict_rfc().with_index_normalization(true),
+            "http://foo.com/dir/"
+        ));
+
+        // Enabling or disabling normalization keeps the index files.
+        let matcher = |enabled| {
+            DefaultMatcher::default()
+                .with_index_files(&["index.php"])
+                .with_index_normalization(enabled)
+        };
+        assert!(check(matcher(true), "http://foo.com/php/"));
+        assert!(!check(matcher(true), "http://foo.com/dir/"));
+        assert!(!check(matcher(false), "http://foo.com/php/"));
+        let matcher = DefaultMatcher::default()
+            .with_index_normalization(false)
+            .with_index_files(&["index.php"]);
+        assert!(!check(matcher, "http://foo.com/php/"));
+        let matcher = DefaultMatcher::strict_rfc()
+            .with_index_files(&["index.php"])
+            .with_index_normalization(true);
+        assert!(check(matcher, "http://foo.com/php/"));
+    }
+
+    #[test]
+    fn test_explain() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /\n\
+        allow: /fish\n\
+        user-agent: *\n\
+        allow: /public\n";
+        let explain = |user_agent: &str, url: &str| {
+            let mut matcher = DefaultMatcher::default().with_match_recording(true);
+            matcher.one_agent_allowed_by_robots(robotstxt, user_agent, url);
+            matcher.explain()
+        };
+        assert_eq!(
+            "Allowed by 'Allow: /fish' (line 3) in the FooBot group.",
+            explain("FooBot", "http://foo.com/fish")
+        );
+        assert_eq!(
+            "Disallowed by 'Disallow: /' (line 2) in the FooBot group.",
+            explain("FooBot", "http://foo.com/cat")
+        );
+        assert_eq!(
+            "Allowed by 'Allow: /public' (line 5) in the global group, as no group names BarBot.",
+            explain("BarBot", "http://foo.com/public")
+        );
+        assert_eq!(
+            "Allowed, as no group names BarBot and no global rule matches.",
+            explain("BarBot", "http://foo.com/cat")
+        );
+    }
+
+    #[test]
+    fn test_query_param_sorting() {
+        assert_eq!("/p", sort_query_params("/p"));
+        assert_eq!("/p?a=1&b=2", sort_query_params("/p?a=1&b=2"));
+        assert_eq!("/p?a=1&b=2", sort_query_params("/p?b=2&a=1"));
+        assert_eq!("/p?&a=1&c&c=3$", sort_query_params("/p?c=3&c&a=1&$"));
+        assert!(matches!(sort_query_params("/p?a&b$"), Cow::Borrowed(_)));
+
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /p?a=1&b=2\n\
+        disallow: /q?b=2&a=1$\n\
+        allow: /r?b=*&a=1\n\
+        disallow: /r\n";
+        let check = |matcher: DefaultMatcher, url: &str| {
+            let mut matcher = matcher;
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        };
+        let sorting = || DefaultMatcher::default().with_query_param_sorting(true);
+
+        assert!(!check(DefaultMatcher::default(), "/p?a=1&b=2"));
+        assert!(check(DefaultMatcher::default(), "/p?b=2&a=1"));
+        assert!(!check(sorting(), "/p?a=1&b=2"));
+        assert!(!check(sorting(), "/p?b=2&a=1"));
+        assert!(!check(sorting(), "/p?b=2&c=3&a=1"));
+        assert!(check(sorting(), "/p?a=2&b=1"));
+
+        assert!(check(DefaultMatcher::default(), "/q?a=1&b=2"));
+        assert!(!check(sorting(), "/q?a=1&b=2"));
+        assert!(check(sorting(), "/q?a=1&b=2&c=3"));
+
+        assert!(!check(DefaultMatcher::default(), "/r?a=1&b=2"));
+        assert!(check(sorting(), "/r?a=1&b=2"));
+    }
+
+    #[test]
+    fn test_match_work_limit() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /*x\n\
+        allow: /public\n";
+        let long_path = format!("/public/{}", "a".repeat(1000));
+        let check = |matcher: DefaultMatcher, url: &str| {
+            let mut matcher = matcher;
+            let allowed = matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url);
+            (allowed, matcher.work_limit_exceeded())
+        };
+
+        assert_eq!((true, false), check(DefaultMatcher::default(), &long_path));
+        // '/*x' on a path of 1008 characters takes 3 * 1009 of work.
+        let limited = |max_work, fallback| {
+            DefaultMatcher::default().with_match_work_limit(max_work, fallback)
+        };
+        assert_eq!(
+            (true, false),
+            check(limited(3027, WorkLimitFallback::Disallow), &long_path)
+        );
+        assert_eq!(
+            (false, true),
+            check(limited(3026, WorkLimitFallback::Disallow), &long_path)
+        );
+        assert_eq!(
+            (true, true),
+            check(
+                limited(80, WorkLimitFallback::Allow),
+                "/x/aaaaaaaaaaaaaaaaaaaaaaaaa"
+            )
+        );
+        // Patterns without wildcards are never limited.
+        let mut matcher = limited(0, WorkLimitFallback::Allow);
+        assert!(!matcher.one_agent_allowed_by_robots(
+            "user-agent: *\ndisallow: /x\n",
+            "FooBot",
+            "/x"
+        ));
+        assert!(!matcher.work_limit_exceeded());
+        // The limit is per check.
+        let mut matcher = limited(100, WorkLimitFallback::Allow);
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", &long_path));
+        assert!(matcher.work_limit_exceeded());
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "/ax"));
+        assert!(!matcher.work_limit_exceeded());
+        // Without a limit, even the longest lines the parser keeps are matched.
+        let robotstxt = format!("user-agent: *\ndisallow: /*{}\n", "a".repeat(16000));
+        let path = format!("/{}", "b".repeat(7000));
+        let mut matcher = DefaultMatcher::default();
+        assert!(matcher.one_agent_allowed_by_robots(&robotstxt, "FooBot", &path));
+        assert!(!matcher.work_limit_exceeded());
+    }
+
+    #[test]
+    fn test_work_limit_decision_reason() {
+        let robotstxt = "user-agent: *\n\
+        disallow: /*x\n\
+        \n\
+        user-agent: FooBot\n\
+        allow: /public\n\
+        disallow: /*y\n";
+        let path = "/public/aaaaaaaaaaaaaaaaaaaa";
+        let mut matcher =
+            DefaultMatcher::default().with_match_work_limit(50, WorkLimitFallback::Disallow);
+
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", path));
+        assert!(matcher.work_limit_exceeded());
+        assert_eq!(DecisionReason::WorkLimitExceeded, matcher.decision_reason());
+        assert_eq!(6, matcher.matching_line());
+        assert_eq!(
+            "Disallowed, as the pattern on line 6 needs more matching work than allowed.",
+            matcher.explain()
+        );
+
+        let robotstxt = "user-agent: *\n\
+        disallow: /*x\n\
+        \n\
+        user-agent: FooBot\n\
+        allow: /public\n";
+        // The global group is ignored for FooBot, so its pattern doesn't count.
+        assert!(matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", path));
+        assert!(!matcher.work_limit_exceeded());
+        assert_eq!(DecisionReason::SpecificAllow, matcher.decision_reason());
+        assert_eq!(5, matcher.matching_line());
+
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "BarBot", path));
+        assert!(matcher.work_limit_exceeded());
+        assert_eq!(DecisionReason::WorkLimitExceeded, matcher.decision_reason());
+        assert_eq!(2, matcher.matching_line());
+        assert_eq!(
+            "Disallowed, as the pattern on line 2 needs more matching work than allowed.",
+            matcher.explain()
+        );
+    }
+
+    #[test]
+    fn test_allowed_by_robots_bounded() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /private\n\
+        allow: /*.html\n";
+        let mut matcher = DefaultMatcher::default();
+        for url in &["/", "/private", "/private/a.html"] {
+            let allowed = matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url);
+            assert_eq!(
+                Ok(allowed),
+                matcher.allowed_by_robots_bounded(robotstxt, vec!["FooBot"], url, 1000)
+            );
+        }
+        // 53 bytes, then 8 for '/private' and 7 * 16 for '/*.html' on '/private/a.html'.
+        let check = |matcher: &mut DefaultMatcher, budget| {
+            matcher.allowed_by_robots_bounded(robotstxt, vec!["FooBot"], "/private/a.html", budget)
+        };
+        assert_eq!(Ok(false), check(&mut matcher, 53 + 8 + 7 * 16));
+        assert_eq!(
+            Err(BudgetExceeded),
+            check(&mut matcher, 53 + 8 + 7 * 16 - 1)
+        );
+        // The budget is per check, unbounded checks are unaffected.
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "/private/a.html"));
+        assert_eq!(Ok(false), check(&mut matcher, 1000));
+
+        // Parsing a huge robots.txt stops soon after the budget is spent.
+        let huge = format!(
+            "user-agent: *\n{}disallow: /\n",
+            "# Filler\n".repeat(100_000)
+        );
+        struct Counter(DefaultMatcher<'static>, u32);
+        impl RobotsParseHandler for Counter {
+            fn handle_robots_start(&mut self) {
+                self.0.handle_robots_start();
+            }
+            fn handle_robots_end(&mut self) {}
+            fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+                self.0.handle_user_agent(line_num, user_agent);
+            }
+            fn handle_allow(&mut self, line_num: u32, value: &str) {
+                self.0.handle_allow(line_num, value);
+            }
+            fn handle_disallow(&mut self, line_num: u32, value: &str) {
+                self.0.handle_disallow(line_num, value);
+            }
+            fn handle_sitemap(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_unknown_action(&mut self, _line_num: u32, _action: &str, _value: &str) {}
+            fn handle_comment(&mut self, _line_num: u32, _comment: &str) {
+                self.1 += 1;
+            }
+            fn handle_progress(&mut self, bytes_processed: usize, total_bytes: usize) {
+                self.0.handle_progress(bytes_processed, total_bytes);
+            }
+            fn stop_parsing(&self) -> bool {
+                self.0.stop_parsing()
+            }
+        }
+        let mut counter = Counter(DefaultMatcher::default(), 0);
+        counter.0.work_budget = Some(1000);
+        RobotsTxtParser::new(&huge, &mut counter)
+            .with_progress_interval(1)
+            .parse();
+        assert!(counter.0.budget_exceeded);
+        // 109 comments fit in the budget, the 110th goes over it and one more
+        // line is parsed before stopping.
+        assert_eq!(111, counter.1);
+
+        let mut matcher = DefaultMatcher::default();
+        assert_eq!(
+            Err(BudgetExceeded),
+            matcher.allowed_by_robots_bounded(&huge, vec!["FooBot"], "/", 100_000)
+        );
+        assert_eq!(
+            Ok(false),
+            matcher.allowed_by_robots_bounded(&huge, vec!["FooBot"], "/", 1_000_000)
+        );
+    }
+
+    #[test]
+    fn test_comma_separated_agents() {
+        let robotstxt = "user-agent: FooBot, BarBot,,BazBot/1.0\n\
+        disallow: /a\n\
+        \n\
+        user-agent: QuxBot,*\n\
+        disallow: /b\n";
+        let check = |matcher: DefaultMatcher, agent: &str, url: &str| {
+            let mut matcher = matcher;
+            matcher.one_agent_allowed_by_robots(robotstxt, agent, url)
+        };
+        let lenient = || DefaultMatcher::default().with_comma_separated_agents(true);
+
+        // Only the first agent of a list is recognized by default.
+        assert!(!check(DefaultMatcher::default(), "FooBot", "/a"));
+        assert!(check(DefaultMatcher::default(), "BarBot", "/a"));
+        assert!(check(DefaultMatcher::default(), "BazBot", "/a"));
+        assert!(!check(DefaultMatcher::default(), "QuxBot", "/b"));
+        assert!(check(DefaultMatcher::default(), "OtherBot", "/b"));
+
+        for agent in &["FooBot", "BarBot", "BazBot"] {
+            assert!(!check(lenient(), agent, "/a"), "{}", agent);
+            assert!(check(lenient(), agent, "/b"), "{}", agent);
+        }
+        assert!(!check(lenient(), "QuxBot", "/b"));
+        assert!(check(lenient(), "QuxBot", "/a"));
+        assert!(!check(lenient(), "OtherBot", "/b"));
+    }
+
+    #[test]
+    fn test_max_path_length() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /*x$\n\
+        allow: /*y\n\
+        disallow: /$\n";
+        let huge = format!("/{}x", "a".repeat(4 * 1024 * 1024));
+        let check = |matcher: DefaultMatcher, url: &str| {
+            let mut matcher = matcher;
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        };
+        let capped = |policy| DefaultMatcher::default().with_max_path_length(2083, policy);
+
+        assert!(!check(DefaultMatcher::default(), &huge));
+        assert!(check(capped(LongPathPolicy::Truncate), &huge));
+        assert!(!check(capped(LongPathPolicy::Reject), &huge));
+
+        // Paths up to the maximum length are unaffected.
+        let longest = format!("/{}x", "a".repeat(2081));
+        assert!(!check(capped(LongPathPolicy::Truncate), &longest));
+        assert!(!check(capped(LongPathPolicy::Reject), &longest));
+        let too_long = format!("/{}x", "a".repeat(2082));
+        assert!(check(capped(LongPathPolicy::Truncate), &too_long));
+        assert!(!check(capped(LongPathPolicy::Reject), &too_long));
+
+        // Rejected paths match no rule.
+        let mut matcher = capped(LongPathPolicy::Reject).with_match_recording(true);
+        let url = format!("{}y", huge);
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", &url));
+        assert!(matcher.matching_rules().is_empty());
+        assert_eq!(DecisionReason::PathTooLong, matcher.decision_reason());
+        assert_eq!(0, matcher.matching_line());
+        assert_eq!(
+            "Disallowed, as the path is longer than the maximum of 2083 bytes.",
+            matcher.explain()
+        );
+        // The path of the next check is matched again.
+        assert!(!matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", "/"));
+        assert_eq!(DecisionReason::SpecificDisallow, matcher.decision_reason());
+        assert_eq!(4, matcher.matching_line());
+
+        // Truncation doesn't split characters.
+        let path = "/éé";
+        for max_length in 0..path.len() {
+            let matcher = DefaultMatcher::default()
+                .with_max_path_length(max_length, LongPathPolicy::Truncate);
+            let truncated_to_root = max_length == 1 || max_length == 2;
+            assert_eq!(!truncated_to_root, check(matcher, path), "{}", max_length);
+        }
+    }
+
+    #[test]
+    fn test_specific_group_over_global() {
+        let allowed = |robotstxt: &str, url: &str| {
+            let mut matcher = DefaultMatcher::default();
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        };
+        let specific = "user-agent: FooBot\n\
+        disallow: /specific\n";
+        let global = "user-agent: *\n\
+        disallow: /global\n\
+        disallow: /specific/global\n";
+
+        // The global group is ignored wherever it appears.
+        for robotstxt in &[
+            format!("{}\n{}", specific, global),
+            format!("{}\n{}", global, specific),
+        ] {
+            assert!(!allowed(robotstxt, "http://foo.com/specific"));
+            assert!(!allowed(robotstxt, "http://foo.com/specific/global"));
+            assert!(allowed(robotstxt, "http://foo.com/global"));
+        }
+
+        // Even if none of the specific rules matches the path.
+        let robotstxt = "user-agent: *\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        allow: /public\n";
+        assert!(allowed(robotstxt, "http://foo.com/public"));
+        assert!(allowed(robotstxt, "http://foo.com/private"));
+
+        // A global allow doesn't override a specific disallow.
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /\n\
+        \n\
+        user-agent: *\n\
+        allow: /\n\
+        allow: /private/longer\n";
+        assert!(!allowed(robotstxt, "http://foo.com/private/longer"));
+
+        // A specific group without rules allows everything.
+        let robotstxt = "user-agent: *\n\
+        disallow: /\n\
+        \n\
+        user-agent: FooBot\n\
+        crawl-delay: 10\n";
+        assert!(allowed(robotstxt, "http://foo.com/"));
+
+        // Specific groups are merged, and so are global ones, around each other.
+        let robotstxt = "user-agent: *\n\
+        disallow: /g1\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /s1\n\
+        \n\
+        user-agent: *\n\
+        disallow: /g2\n\
+        \n\
+        user-agent: FooBot\n\
+        disallow: /s2\n";
+        assert!(!allowed(robotstxt, "http://foo.com/s1"));
+        assert!(!allowed(robotstxt, "http://foo.com/s2"));
+        assert!(allowed(robotstxt, "http://foo.com/g1"));
+        assert!(allowed(robotstxt, "http://foo.com/g2"));
+
+        // A group naming both '*' and the agent applies, as a specific group.
+        let robotstxt = "user-agent: *\n\
+        user-agent: FooBot\n\
+        disallow: /shared\n\
+        \n\
+        user-agent: *\n\
+        disallow: /global\n";
+        assert!(!allowed(robotstxt, "http://foo.com/shared"));
+        assert!(allowed(robotstxt, "http://foo.com/global"));
+
+        // Groups of other agents don't count as specific.
+        let robotstxt = "user-agent: BarBot\n\
+        allow: /\n\
+        \n\
+        user-agent: *\n\
+        disallow: /global\n";
+        assert!(!allowed(robotstxt, "http://foo.com/global"));
+
+        // With several agents, any of them having a group ignores the global one.
+        let robotstxt = format!("{}\n{}", global, specific);
+        let mut matcher = DefaultMatcher::default();
+        let agents = vec!["BarBot", "FooBot"];
+        assert!(matcher.allowed_by_robots(&robotstxt, agents.clone(), "http://foo.com/global"));
+        assert!(!matcher.allowed_by_robots(&robotstxt, agents, "http://foo.com/specific"));
+    }
+
+    #[test]
+    fn test_escaped_asterisks() {
+        let robotstxt = "user-agent: FooBot\n\
+        disallow: /a%2Ab\n\
+        disallow: /c%2a\n\
+        disallow: /d*e\n\
+        allow: /f*%2A$\n\
+        disallow: /f\n";
+        let allowed = |matcher: DefaultMatcher, url: &str| {
+            let mut matcher = matcher;
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        };
+        let escaped = || DefaultMatcher::default().with_escaped_asterisks(true);
+
+        // Without the extension, only the wildcard matches a literal '*'.
+        assert!(allowed(DefaultMatcher::default(), "/a*b"));
+        assert!(!allowed(DefaultMatcher::default(), "/a%2Ab"));
+        assert!(!allowed(DefaultMatcher::default(), "/d*e"));
+        assert!(!allowed(DefaultMatcher::default(), "/dxe"));
+
+        assert!(!allowed(escaped(), "/a*b"));
+        assert!(!allowed(escaped(), "/a%2Ab"));
+        assert!(allowed(escaped(), "/axb"));
+        // The parser normalizes the escape sequence to upper case.
+        assert!(!allowed(escaped(), "/c*"));
+        // '*' is still a wildcard, matching a '*' of the path as well.
+        assert!(!allowed(escaped(), "/d*e"));
+        assert!(!allowed(escaped(), "/dxe"));
+        assert!(allowed(escaped(), "/fx*"));
+        assert!(!allowed(escaped(), "/fx"));
+    }
 }
@@ -14,9 +14,11 @@
 // limitations under the License.
 //
 
+use std::borrow::Cow;
+
 use crate::RobotsParseHandler;
 
-#[derive(Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 /// A enum represents key types in robotstxt.
 pub enum ParseKeyType {
     // Generic highlevel fields.
@@ -27,6 +29,9 @@ pub enum ParseKeyType {
     Allow,
     Disallow,
 
+    /// A key registered with [with_custom_key](RobotsTxtParser::with_custom_key()).
+    Custom,
+
     /// Unrecognized field; kept as-is. High number so that additions to the
     /// enumeration above does not change the serialization.
     Unknown = 128,
@@ -39,28 +44,46 @@ pub enum ParseKeyType {
 /// and represent them as an enumeration which allows for faster processing
 /// afterwards.
 /// For unparsable keys, the original string representation is kept.
-pub struct ParsedRobotsKey {
+pub struct ParsedRobotsKey<'a> {
     type_: ParseKeyType,
-    key_text: String,
+    key_text: &'a str,
     /// Allow for typos such as DISALOW in robots.txt.
     allow_typo: bool,
+    /// True if the key was recognized through one of the accepted typos.
+    typo_corrected: bool,
 }
 
-impl Default for ParsedRobotsKey {
+impl Default for ParsedRobotsKey<'_> {
     fn default() -> Self {
         ParsedRobotsKey {
             type_: ParseKeyType::Unknown,
             allow_typo: true,
-            key_text: String::new(),
+            typo_corrected: false,
+            key_text: "",
         }
     }
 }
 
-impl ParsedRobotsKey {
+impl<'a> ParsedRobotsKey<'a> {
     /// Parse given key text. Does not copy the text, so the text_key must stay
     /// valid for the object's life-time or the next `parse()` call.
-    pub fn parse(&mut self, key: &str) {
-        if self.validate_key(key, &["user-agent"], Some(&["useragent", "user agent"])) {
+    pub fn parse(&mut self, key: &'a str) {
+        self.parse_with_custom_keys(key, &[]);
+    }
+
+    /// Same as [parse](Self::parse), but a key equal to one of `custom_keys`,
+    /// ignoring case, is recognized as [ParseKeyType::Custom]. Unlike the
+    /// standard keys, custom keys must match exactly, not only as a prefix, and
+    /// they are checked first.
+    pub fn parse_with_custom_keys(&mut self, key: &'a str, custom_keys: &[String]) {
+        self.typo_corrected = false;
+        if custom_keys
+            .iter()
+            .any(|custom_key| custom_key.eq_ignore_ascii_case(key))
+        {
+            self.type_ = ParseKeyType::Custom;
+            self.key_text = key;
+        } else if self.validate_key(key, &["user-agent"], Some(&["useragent", "user agent"])) {
             self.type_ = ParseKeyType::UserAgent;
         } else if self.validate_key(key, &["allow"], None) {
             self.type_ = ParseKeyType::Allow;
@@ -74,7 +97,7 @@ impl ParsedRobotsKey {
             self.type_ = ParseKeyType::Sitemap;
         } else {
             self.type_ = ParseKeyType::Unknown;
-            self.key_text = key.to_string();
+            self.key_text = key;
         }
     }
 
@@ -83,25 +106,366 @@ impl ParsedRobotsKey {
         &self.type_
     }
 
-    /// If this is an unknown key, get the text.
-    pub fn get_unknown_text(&self) -> String {
-        self.key_text.to_string()
+    /// Returns true if the key was recognized through one of the accepted
+    /// typos, such as `disalow` for [ParseKeyType::Disallow].
+    pub fn is_typo_corrected(&self) -> bool {
+        self.typo_corrected
+    }
+
+    /// If this is an unknown or custom key, get the text.
+    pub fn get_unknown_text(&self) -> &'a str {
+        self.key_text
     }
 
-    fn validate_key(&self, key: &str, targets: &[&str], typo_targets: Option<&[&str]>) -> bool {
+    fn validate_key(&mut self, key: &str, targets: &[&str], typo_targets: Option<&[&str]>) -> bool {
         let key = key.to_lowercase();
         let check = |target: &&str| key.starts_with(&target.to_lowercase());
-        targets.iter().any(check)
-            || (typo_targets.is_some()
-                && self.allow_typo
-                && typo_targets.unwrap().iter().any(check))
+        if targets.iter().any(check) {
+            return true;
+        }
+        self.typo_corrected = self.allow_typo
+            && typo_targets.is_some_and(|typo_targets| typo_targets.iter().any(check));
+        self.typo_corrected
+    }
+}
+
+/// The line splitting rules shared by [Lines] and [split_chunked_lines], fed one
+/// character at a time.
+///
+/// All of `\n`, `\r` and `\r\n` end a line, a byte order mark (U+FEFF) at the
+/// start of the body is skipped and overlong lines are truncated: a line keeps less
+/// than `MAX_LINE_LEN` bytes, the rest of it up to the line break is dropped.
+struct LineSplitter {
+    bom_pos: usize,
+    /// Bytes of the current line kept so far.
+    line_len: usize,
+    last_was_carriage_return: bool,
+}
+
+/// What to do with a character, see [LineSplitter::step].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LineStep {
+    /// Part of the byte order mark, not part of any line.
+    Skip,
+    /// Part of the current line.
+    Keep,
+    /// Past the maximum length of the current line, dropped.
+    Drop,
+    /// A line break, ending the current line. `yields_line` is false for the
+    /// `\n` of a `\r\n`, which only completes the line break.
+    End { yields_line: bool },
+}
+
+impl LineSplitter {
+    const BOM: char = '\u{FEFF}';
+    const UTF_BOM: [usize; 3] = [0xEF, 0xBB, 0xBF];
+    // Certain browsers limit the URL length to 2083 bytes. In a robots.txt, it's
+    // fairly safe to assume any valid line isn't going to be more than many times
+    // that max url length of 2KB. We want some padding for
+    // UTF-8 encoding/nulls/etc. but a much smaller bound would be okay as well.
+    // If so, we can ignore the chars on a line past that.
+    const MAX_LINE_LEN: usize = 2083 * 8;
+
+    fn new(skip_bom: bool) -> Self {
+        LineSplitter {
+            bom_pos: if skip_bom { 0 } else { Self::UTF_BOM.len() },
+            line_len: 0,
+            last_was_carriage_return: false,
+        }
+    }
+
+    fn step(&mut self, ch: char) -> LineStep {
+        // Google-specific optimization: UTF-8 byte order marks should never
+        // appear in a robots.txt file, but they do nevertheless. Skipping
+        // possible BOM-prefix in the first bytes of the input: the decoded
+        // U+FEFF, or its bytes read as characters, as Google does.
+        if self.bom_pos == 0 && ch == Self::BOM {
+            self.bom_pos = Self::UTF_BOM.len();
+            return LineStep::Skip;
+        }
+        if self.bom_pos < Self::UTF_BOM.len() && ch as usize == Self::UTF_BOM[self.bom_pos] {
+            self.bom_pos += 1;
+            return LineStep::Skip;
+        }
+        self.bom_pos = Self::UTF_BOM.len();
+
+        if ch != '\n' && ch != '\r' {
+            // Put in next spot on current line, as long as there's room.
+            if self.line_len < Self::MAX_LINE_LEN - 1 {
+                self.line_len += ch.len_utf8();
+                LineStep::Keep
+            } else {
+                LineStep::Drop
+            }
+        } else {
+            // Only emit an empty line if this was not due to the second character
+            // of the DOS line-ending \r\n .
+            let is_crlf_continuation =
+                self.line_len == 0 && self.last_was_carriage_return && ch == '\n';
+            self.line_len = 0;
+            self.last_was_carriage_return = ch == '\r';
+            LineStep::End {
+                yields_line: !is_crlf_continuation,
+            }
+        }
+    }
+}
+
+/// Splits a robots.txt body into numbered lines, the way [RobotsTxtParser] sees
+/// them, see [LineSplitter]. The last line is always yielded, even if empty.
+pub(crate) struct Lines<'a> {
+    robots_body: &'a str,
+    chars: std::str::CharIndices<'a>,
+    splitter: LineSplitter,
+    line_num: u32,
+    // Bounds of the part of the current line kept so far.
+    start: usize,
+    end: usize,
+    // Terminator of the last line yielded, None for the last line of the body.
+    line_ending: Option<LineEnding>,
+    done: bool,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(robots_body: &'a str) -> Self {
+        Lines {
+            robots_body,
+            chars: robots_body.char_indices(),
+            splitter: LineSplitter::new(true),
+            line_num: 0,
+            start: 0,
+            end: 0,
+            line_ending: None,
+            done: false,
+        }
+    }
+
+    /// Returns true if the last line yielded was ended by a lone `\r`, the old
+    /// Mac line ending, rather than by `\n` or `\r\n`.
+    pub(crate) fn ended_with_lone_carriage_return(&self) -> bool {
+        self.line_ending == Some(LineEnding::Cr)
+    }
+
+    /// Don't skip a byte order mark (U+FEFF or its UTF-8 bytes read as
+    /// characters) at the start of the body, it's then part of the first line.
+    pub(crate) fn without_bom_skipping(mut self) -> Self {
+        self.splitter = LineSplitter::new(false);
+        self
+    }
+
+    /// Returns the number of bytes of the body consumed so far.
+    pub(crate) fn bytes_processed(&self) -> usize {
+        self.robots_body.len() - self.chars.as_str().len()
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (u32, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        for (index, ch) in &mut self.chars {
+            let next = index + ch.len_utf8();
+            match self.splitter.step(ch) {
+                LineStep::Skip => {
+                    self.start = next;
+                    self.end = next;
+                }
+                LineStep::Keep => self.end = next,
+                LineStep::Drop => {}
+                LineStep::End { yields_line } => {
+                    let line = &self.robots_body[self.start..self.end];
+                    self.start = next;
+                    self.end = next;
+                    self.line_ending = if ch == '\n' {
+                        Some(LineEnding::Lf)
+                    } else if self.robots_body.as_bytes().get(next) == Some(&b'\n') {
+                        Some(LineEnding::CrLf)
+                    } else {
+                        Some(LineEnding::Cr)
+                    };
+                    if yields_line {
+                        self.line_num += 1;
+                        return Some((self.line_num, line));
+                    }
+                }
+            }
+        }
+        self.done = true;
+        self.line_ending = None;
+        self.line_num += 1;
+        Some((self.line_num, &self.robots_body[self.start..self.end]))
+    }
+}
+
+/// A line terminator.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LineEnding {
+    Lf,
+    Cr,
+    CrLf,
+}
+
+/// The number of each line terminator found in a robots.txt, see
+/// [detect_line_endings].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LineEndingReport {
+    /// Lines ended by `\n`, the Unix line ending.
+    pub lf: usize,
+    /// Lines ended by a lone `\r`, the old Mac line ending.
+    pub cr: usize,
+    /// Lines ended by `\r\n`, the DOS line ending.
+    pub crlf: usize,
+}
+
+impl LineEndingReport {
+    /// Returns true if more than one kind of line terminator was found.
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.cr, self.crlf]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count()
+            > 1
+    }
+}
+
+/// Counts the line terminators of a robots.txt, the way [RobotsTxtParser] splits
+/// it into lines. Mixed line endings are accepted by the parser, but some other
+/// tools mishandle them.
+/// ```rust
+/// use robotstxt::parser::{detect_line_endings, LineEndingReport};
+///
+/// let report = detect_line_endings("User-agent: *\r\nDisallow: /a\nAllow: /b\r\n");
+/// assert_eq!(LineEndingReport { lf: 1, cr: 0, crlf: 2 }, report);
+/// assert_eq!(true, report.is_mixed());
+/// assert_eq!(false, detect_line_endings("User-agent: *\rDisallow: /\r").is_mixed());
+/// ```
+pub fn detect_line_endings(robots_body: &str) -> LineEndingReport {
+    let mut report = LineEndingReport::default();
+    let mut lines = Lines::new(robots_body);
+    while lines.next().is_some() {
+        match lines.line_ending {
+            Some(LineEnding::Lf) => report.lf += 1,
+            Some(LineEnding::Cr) => report.cr += 1,
+            Some(LineEnding::CrLf) => report.crlf += 1,
+            None => {}
+        }
     }
+    report
+}
+
+/// Splits a robots.txt body given in pieces into numbered lines, exactly as
+/// [Lines] splits the concatenated body. A line within a single piece is
+/// borrowed, only lines spanning several pieces are copied.
+///
+/// `on_line` gets the line number, the line and whether it was ended by a lone
+/// `\r`. Splitting stops as soon as it returns false.
+fn split_chunked_lines<'c>(
+    chunks: impl IntoIterator<Item = &'c str>,
+    skip_bom: bool,
+    mut on_line: impl FnMut(u32, &str, bool) -> bool,
+) {
+    let mut splitter = LineSplitter::new(skip_bom);
+    let mut line_num = 0;
+    // Start of the current line in earlier pieces.
+    let mut carry = String::new();
+    // A line ended by '\r', held back until we know whether '\n' follows.
+    let mut pending: Option<(u32, Cow<'c, str>)> = None;
+
+    for chunk in chunks {
+        // Part of the current line kept in this piece.
+        let mut start = 0;
+        let mut end = 0;
+        for (index, ch) in chunk.char_indices() {
+            let next = index + ch.len_utf8();
+            let step = splitter.step(ch);
+            if step == LineStep::Skip {
+                start = next;
+                end = next;
+                continue;
+            }
+
+            if let Some((pending_num, pending_line)) = pending.take() {
+                if !on_line(pending_num, &pending_line, ch != '\n') {
+                    return;
+                }
+            }
+
+            match step {
+                LineStep::Keep => end = next,
+                LineStep::Skip | LineStep::Drop => {}
+                LineStep::End { yields_line } => {
+                    let line = if carry.is_empty() {
+                        Cow::Borrowed(&chunk[start..end])
+                    } else {
+                        carry.push_str(&chunk[start..end]);
+                        Cow::Owned(std::mem::take(&mut carry))
+                    };
+                    start = next;
+                    end = next;
+                    if yields_line {
+                        line_num += 1;
+                        if ch == '\r' {
+                            pending = Some((line_num, line));
+                        } else if !on_line(line_num, &line, false) {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        carry.push_str(&chunk[start..end]);
+    }
+
+    if let Some((pending_num, pending_line)) = pending {
+        if !on_line(pending_num, &pending_line, true) {
+            return;
+        }
+    }
+    on_line(line_num + 1, &carry, false);
+}
+
+/// Splits a robots.txt body into numbered lines exactly the way the parser does,
+/// without interpreting them, to build other tools on the same line handling.
+///
+/// All of `\n`, `\r` and `\r\n` end a line, a byte order mark (U+FEFF) at the
+/// start of the body is skipped and overlong lines are truncated. Line numbers start
+/// at 1, and the last line is always yielded, even if empty.
+/// ```rust
+/// use robotstxt::parser::tokenize_lines;
+///
+/// let lines: Vec<_> = tokenize_lines("User-agent: *\r\nDisallow: /\rAllow: /a\n").collect();
+/// assert_eq!(
+///     vec![(1, "User-agent: *"), (2, "Disallow: /"), (3, "Allow: /a"), (4, "")],
+///     lines
+/// );
+/// ```
+pub fn tokenize_lines(robots_body: &str) -> impl Iterator<Item = (u32, &str)> + '_ {
+    Lines::new(robots_body)
 }
 
 /// A robotstxt parser.
 pub struct RobotsTxtParser<'a, Handler: RobotsParseHandler> {
     robots_body: &'a str,
     handler: &'a mut Handler,
+    equals_separator: bool,
+    max_groups: Option<usize>,
+    lone_carriage_return_warnings: bool,
+    strip_quotes: bool,
+    progress_interval: Option<u32>,
+    custom_keys: Vec<String>,
+    google_extensions: bool,
+    line_continuations: bool,
+    /// Line number and text of a line ended by a '\\' waiting for the next line.
+    continued_line: Option<(u32, String)>,
+    /// Number of user-agent groups seen so far.
+    group_count: usize,
+    /// True if the last directive emitted was a user-agent line.
+    in_user_agents: bool,
+    /// True if a group was started and not ended yet.
+    in_group: bool,
 }
 
 impl<'a, Handler: RobotsParseHandler> RobotsTxtParser<'a, Handler> {
@@ -109,92 +473,244 @@ impl<'a, Handler: RobotsParseHandler> RobotsTxtParser<'a, Handler> {
         RobotsTxtParser {
             robots_body,
             handler,
+            equals_separator: false,
+            max_groups: None,
+            lone_carriage_return_warnings: false,
+            strip_quotes: false,
+            progress_interval: None,
+            custom_keys: vec![],
+            google_extensions: true,
+            line_continuations: false,
+            continued_line: None,
+            group_count: 0,
+            in_user_agents: false,
+            in_group: false,
         }
     }
 
+    /// Creates a parser without a body of its own, for a robots.txt given in
+    /// pieces to [parse_chunks](Self::parse_chunks).
+    /// ```rust
+    /// use robotstxt::collector::GroupCollector;
+    /// use robotstxt::parser::RobotsTxtParser;
+    ///
+    /// let mut collector = GroupCollector::default();
+    /// RobotsTxtParser::for_chunks(&mut collector).parse_chunks(vec!["user-agent: *\ndis", "allow: /\n"]);
+    /// assert_eq!("/", collector.groups()[0].rules[0].pattern);
+    /// ```
+    pub fn for_chunks(handler: &'a mut Handler) -> Self {
+        Self::new("", handler)
+    }
+
+    /// Also accept `=` as the separator of key and value, as in
+    /// `Disallow = /path`. Google doesn't accept it, so this is off by default.
+    pub fn with_equals_separator(mut self, enabled: bool) -> Self {
+        self.equals_separator = enabled;
+        self
+    }
+
+    /// Only process the first `max_groups` user-agent groups. The user-agent,
+    /// allow and disallow lines of any further group are skipped, while other
+    /// directives such as sitemaps are still emitted. This bounds the work spent
+    /// on a robots.txt made of a huge number of tiny groups. Unlimited by default.
+    pub fn with_max_groups(mut self, max_groups: usize) -> Self {
+        self.max_groups = Some(max_groups);
+        self
+    }
+
+    /// Report every line ended by a lone `\r` to
+    /// [handle_lone_carriage_return](RobotsParseHandler::handle_lone_carriage_return()).
+    /// Such line endings are still accepted either way, but they are rare enough
+    /// to hint at a corrupted file. Disabled by default.
+    pub fn with_lone_carriage_return_warnings(mut self, enabled: bool) -> Self {
+        self.lone_carriage_return_warnings = enabled;
+        self
+    }
+
+    /// Report the progress of [parse](Self::parse) to
+    /// [handle_progress](RobotsParseHandler::handle_progress()) every `lines`
+    /// lines, and once more when the whole body is processed. Meant for tools
+    /// showing the progress of huge robots.txt files. Not reported by default.
+    pub fn with_progress_interval(mut self, lines: u32) -> Self {
+        self.progress_interval = Some(lines.max(1));
+        self
+    }
+
+    /// Accept the Google-specific leniencies which the robots.txt draft doesn't
+    /// specify: typos in keys such as `disalow` or `useragent`, a key and value
+    /// separated by whitespace instead of a colon, and a UTF-8 byte order mark
+    /// at the start of the body. Enabled by default, as Google does.
+    pub fn with_google_extensions(mut self, enabled: bool) -> Self {
+        self.google_extensions = enabled;
+        self
+    }
+
+    /// Join a line ended by a backslash with the next line, as some generators
+    /// wrap long lines this way: `Disallow: /a\` followed by `b` is parsed as
+    /// `Disallow: /ab`, reported on the line of the first part. The draft has no
+    /// line continuations, so this is disabled by default.
+    pub fn with_line_continuations(mut self, enabled: bool) -> Self {
+        self.line_continuations = enabled;
+        self
+    }
+
+    /// Recognize `key` as a custom directive, reported to
+    /// [handle_custom_directive](RobotsParseHandler::handle_custom_directive())
+    /// rather than as an unknown action. The key must match exactly, ignoring
+    /// case, and its value is passed as is. May be called several times to
+    /// register several keys; only the standard keys are recognized by default.
+    pub fn with_custom_key(mut self, key: &str) -> Self {
+        self.custom_keys.push(key.trim().to_string());
+        self
+    }
+
+    /// Strip a pair of surrounding ASCII quotes, `"` or `'`, from user-agent,
+    /// allow and disallow values, so that `User-agent: "FooBot"` names `FooBot`.
+    /// Google takes quotes literally, so this is disabled by default.
+    pub fn with_quote_stripping(mut self, enabled: bool) -> Self {
+        self.strip_quotes = enabled;
+        self
+    }
+
     /// Parse body of this Parser's robots.txt and emit parse callbacks. This will accept
     /// typical typos found in robots.txt, such as 'disalow'.
     ///
     /// Note, this function will accept all kind of input but will skip
     /// everything that does not look like a robots directive.
     pub fn parse(&mut self) {
-        let utf_bom = [0xEF, 0xBB, 0xBF];
-        // Certain browsers limit the URL length to 2083 bytes. In a robots.txt, it's
-        // fairly safe to assume any valid line isn't going to be more than many times
-        // that max url length of 2KB. We want some padding for
-        // UTF-8 encoding/nulls/etc. but a much smaller bound would be okay as well.
-        // If so, we can ignore the chars on a line past that.
-        let max_line_len = 2083 * 8;
-        let mut line_num = 0;
-        let mut bom_pos = 0;
-        let mut last_was_carriage_return = false;
-        self.handler.handle_robots_start();
-
-        let mut start = 0;
-        let mut end = 0;
-        // We should skip the rest part which exceed max_line_len
-        // in the current line.
-        let mut skip_exceed = 0;
-        for (ch, char_len_utf8) in self
-            .robots_body
-            .chars()
-            .map(|ch| (ch as usize, ch.len_utf8()))
-        {
-            // Google-specific optimization: UTF-8 byte order marks should never
-            // appear in a robots.txt file, but they do nevertheless. Skipping
-            // possible BOM-prefix in the first bytes of the input.
-            if bom_pos < utf_bom.len() && ch == utf_bom[bom_pos] {
-                bom_pos += 1;
-                start += char_len_utf8;
-                end += char_len_utf8;
-                continue;
+        self.start();
+        let mut lines = Lines::new(self.robots_body);
+        if !self.google_extensions {
+            lines = lines.without_bom_skipping();
+        }
+        while let Some((line_num, line)) = lines.next() {
+            if !self.process_line(line_num, line, lines.ended_with_lone_carriage_return()) {
+                break;
             }
-            bom_pos = utf_bom.len();
-
-            if ch != 0x0A && ch != 0x0D {
-                // Non-line-ending char case.
-                // Put in next spot on current line, as long as there's room.
-                if (end - start) < max_line_len - 1 {
-                    end += char_len_utf8;
-                } else {
-                    skip_exceed += 1;
+            if let Some(interval) = self.progress_interval {
+                // The end of the body is reported once, after the loop.
+                if line_num % interval == 0 && lines.bytes_processed() < self.robots_body.len() {
+                    self.handler
+                        .handle_progress(lines.bytes_processed(), self.robots_body.len());
                 }
-            } else {
-                // Line-ending character char case.
-                // Only emit an empty line if this was not due to the second character
-                // of the DOS line-ending \r\n .
-                let is_crlf_continuation = end == start && last_was_carriage_return && ch == 0x0A;
-                if !is_crlf_continuation {
-                    line_num += 1;
-                    self.parse_and_emit_line(line_num, &self.robots_body[start..end]);
-                }
-                // Add skip_exceed to skip those chars.
-                end += skip_exceed + char_len_utf8;
-                start = end;
-                last_was_carriage_return = ch == 0x0D;
-                skip_exceed = 0;
             }
         }
-        line_num += 1;
-        self.parse_and_emit_line(line_num, &self.robots_body[start..end]);
+        self.finish();
+        if self.progress_interval.is_some() {
+            self.handler
+                .handle_progress(lines.bytes_processed(), self.robots_body.len());
+        }
         self.handler.handle_robots_end();
     }
 
+    /// Same as [parse](Self::parse), but the robots.txt is given in pieces,
+    /// e.g. as read from a buffered reader, rather than as the body of this
+    /// parser, which is ignored: see [for_chunks](Self::for_chunks). Lines may span pieces, and are numbered and emitted exactly as if
+    /// the pieces were concatenated, without concatenating them.
+    pub fn parse_chunks<'c>(&mut self, chunks: impl IntoIterator<Item = &'c str>) {
+        self.start();
+        let skip_bom = self.google_extensions;
+        split_chunked_lines(chunks, skip_bom, |line_num, line, lone_carriage_return| {
+            self.process_line(line_num, line, lone_carriage_return)
+        });
+        self.finish();
+        self.handler.handle_robots_end();
+    }
+
+    fn start(&mut self) {
+        self.continued_line = None;
+        self.group_count = 0;
+        self.in_user_agents = false;
+        self.in_group = false;
+        self.handler.handle_robots_start();
+    }
+
+    /// Parses a line and emits its callbacks. Returns false once the handler
+    /// wants parsing to stop.
+    fn process_line(&mut self, line_num: u32, line: &str, lone_carriage_return: bool) -> bool {
+        if self.lone_carriage_return_warnings && lone_carriage_return {
+            self.handler.handle_lone_carriage_return(line_num);
+        }
+        if self.line_continuations {
+            let (line_num, line) = match self.continued_line.take() {
+                Some((first_line_num, mut joined)) => {
+                    joined.push_str(line);
+                    (first_line_num, Cow::Owned(joined))
+                }
+                None => (line_num, Cow::Borrowed(line)),
+            };
+            if let Some(continued) = line.strip_suffix('\\') {
+                self.continued_line = Some((line_num, continued.to_string()));
+                return true;
+            }
+            self.parse_and_emit_line(line_num, &line);
+        } else {
+            self.parse_and_emit_line(line_num, line);
+        }
+        !self.handler.stop_parsing()
+    }
+
+    /// Parses a line still waiting for its continuation at the end of the body,
+    /// and ends the last group.
+    fn finish(&mut self) {
+        if let Some((line_num, line)) = self.continued_line.take() {
+            self.parse_and_emit_line(line_num, &line);
+        }
+        self.end_group();
+    }
+
+    fn end_group(&mut self) {
+        if self.in_group {
+            self.in_group = false;
+            self.handler.handle_group_end();
+        }
+    }
+
     /// Attempts to parse a line of robots.txt into a key/value pair.
     ///
     /// On success, the parsed key and value, and true, are returned. If parsing is
     /// unsuccessful, `parse_key_value` returns two empty strings and false.
+    ///
+    /// The colon may be omitted for any key, e.g. `Disallow /a/b`, in which case
+    /// the key and value are separated by whitespace. This is only accepted if
+    /// the line has exactly two sequences of non-whitespace characters, so
+    /// `Disallow /a b` is rejected. Slashes don't split a sequence. Since any
+    /// colon is taken as the separator first, a colon-less line whose value
+    /// contains a colon, like `Sitemap http://foo.com/sitemap.xml`, is split at
+    /// the colon of the value.
     pub fn parse_key_value(line: &str) -> (&str, &str, bool) {
+        Self::split_key_value(line, false, true)
+    }
+
+    /// Same as [parse_key_value](Self::parse_key_value), but if the line has no
+    /// colon, `=` is accepted as the separator as well, provided the key before
+    /// it is a single word.
+    pub fn parse_key_value_lenient(line: &str) -> (&str, &str, bool) {
+        Self::split_key_value(line, true, true)
+    }
+
+    fn split_key_value(
+        line: &str,
+        equals_separator: bool,
+        whitespace_separator: bool,
+    ) -> (&str, &str, bool) {
         let mut line = line;
         // Remove comments from the current robots.txt line.
         if let Some(comment) = line.find('#') {
-            line = &line[..comment].trim();
+            line = &line[..comment];
         }
+        // Surrounding whitespace must not be taken for a colon-less separator.
+        let line = line.trim();
 
         // Rules must match the following pattern:
         //   <key>[ \t]*:[ \t]*<value>
         let mut sep = line.find(':');
-        if sep.is_none() {
+        if sep.is_none() && equals_separator {
+            sep = line
+                .find('=')
+                .filter(|&equals| !line[..equals].trim().contains([' ', '\t']));
+        }
+        if sep.is_none() && whitespace_separator {
             // Google-specific optimization: some people forget the colon, so we need to
             // accept whitespace in its stead.
             let white = " \t";
@@ -236,35 +752,103 @@ impl<'a, Handler: RobotsParseHandler> RobotsTxtParser<'a, Handler> {
     }
 
     fn parse_and_emit_line(&mut self, current_line: u32, line: &str) {
-        match Self::parse_key_value(line) {
-            (_, _, false) => {}
-            (string_key, value, true) => {
-                let mut key = ParsedRobotsKey::default();
-                key.parse(string_key);
+        let comment = line.find('#');
+        match Self::split_key_value(line, self.equals_separator, self.google_extensions) {
+            (_, _, false) => {
+                let content = comment.map_or(line, |comment| &line[..comment]);
+                if !content.trim().is_empty() {
+                    self.handler.handle_parse_error(current_line, line);
+                }
+            }
+            (string_key, mut value, true) => {
+                let mut key = ParsedRobotsKey {
+                    allow_typo: self.google_extensions,
+                    ..Default::default()
+                };
+                key.parse_with_custom_keys(string_key, &self.custom_keys);
+                if key.is_typo_corrected() {
+                    self.handler
+                        .handle_typo_correction(current_line, string_key, *key.get_type());
+                }
+                if let ParseKeyType::UserAgent | ParseKeyType::Allow | ParseKeyType::Disallow =
+                    key.get_type()
+                {
+                    if self.strip_quotes {
+                        value = strip_quotes(value);
+                    }
+                }
                 if Self::need_escape_value_for_key(&key) {
-                    let value = escape_pattern(value);
-                    self.emit(current_line, &key, &value);
+                    let escaped_value = escape_pattern(value);
+                    self.emit(current_line, &key, &escaped_value, value);
                 } else {
-                    self.emit(current_line, &key, value);
+                    self.emit(current_line, &key, value, value);
                 }
             }
         }
+        if let Some(comment) = comment {
+            self.handler
+                .handle_comment(current_line, line[comment + 1..].trim());
+        }
     }
 
-    fn emit(&mut self, line: u32, key: &ParsedRobotsKey, value: &str) {
+    /// Emits a directive with its escaped `value`, and its `raw_value` as written
+    /// (after quote stripping).
+    fn emit(&mut self, line: u32, key: &ParsedRobotsKey, value: &str, raw_value: &str) {
+        let is_user_agent = key.get_type() == &ParseKeyType::UserAgent;
+        let starts_group = is_user_agent && !self.in_user_agents;
+        if starts_group {
+            self.group_count += 1;
+        }
+        self.in_user_agents = is_user_agent;
+        let over_max_groups = self
+            .max_groups
+            .is_some_and(|max_groups| self.group_count > max_groups);
+        if starts_group {
+            self.end_group();
+            if !over_max_groups {
+                self.in_group = true;
+                self.handler.handle_group_start(line);
+            }
+        }
+
         match key.get_type() {
+            ParseKeyType::UserAgent | ParseKeyType::Allow | ParseKeyType::Disallow
+                if over_max_groups => {}
             ParseKeyType::UserAgent => self.handler.handle_user_agent(line, value),
             ParseKeyType::Sitemap => self.handler.handle_sitemap(line, value),
-            ParseKeyType::Allow => self.handler.handle_allow(line, value),
-            ParseKeyType::Disallow => self.handler.handle_disallow(line, value),
+            ParseKeyType::Allow => {
+                self.handler.handle_raw_value(line, raw_value, value);
+                self.handler.handle_allow(line, value)
+            }
+            ParseKeyType::Disallow => {
+                self.handler.handle_raw_value(line, raw_value, value);
+                self.handler.handle_disallow(line, value)
+            }
+            ParseKeyType::Custom => {
+                self.handler
+                    .handle_custom_directive(line, key.get_unknown_text(), value)
+            }
             ParseKeyType::Unknown => {
                 self.handler
-                    .handle_unknown_action(line, &key.get_unknown_text(), value)
+                    .handle_unknown_action(line, key.get_unknown_text(), value)
             }
         }
     }
 }
 
+/// Removes a pair of matching ASCII quotes around `value`, if any.
+fn strip_quotes(value: &str) -> &str {
+    for quote in &['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(*quote)
+            .and_then(|value| value.strip_suffix(*quote))
+        {
+            return inner.trim();
+        }
+    }
+    value
+}
+
 const HEX_DIGITS: [char; 16] = [
     '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F',
 ];
@@ -282,37 +866,26 @@ const HEX_DIGITS: [char; 16] = [
 /// If the given path pattern is already adequately escaped,
 /// the original string is returned unchanged.
 pub fn escape_pattern(path: &str) -> String {
+    let bytes = path.as_bytes();
     let mut num_to_escape = 0;
     let mut need_capitalize = false;
 
     // First, scan the buffer to see if changes are needed. Most don't.
-    let mut chars = path.bytes();
-    loop {
-        match chars.next() {
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_escape_sequence(bytes, i) {
             // (a) % escape sequence.
-            Some(c) if c as char == '%' => {
-                match (
-                    chars.next().map(|c| c as char),
-                    chars.next().map(|c| c as char),
-                ) {
-                    (Some(c1), Some(c2)) if c1.is_digit(16) && c2.is_digit(16) => {
-                        if c1.is_ascii_lowercase() || c2.is_ascii_lowercase() {
-                            need_capitalize = true;
-                        }
-                    }
-                    _ => {}
-                }
+            if bytes[i + 1].is_ascii_lowercase() || bytes[i + 2].is_ascii_lowercase() {
+                need_capitalize = true;
             }
-            Some(c) if c >= 0x80 => {
+            i += 3;
+        } else {
+            if bytes[i] >= 0x80 {
                 // (b) needs escaping.
                 num_to_escape += 1;
             }
-            o => {
-                // (c) Already escaped and escape-characters normalized (eg. %2f -> %2F).
-                if o.is_none() {
-                    break;
-                }
-            }
+            // (c) Already escaped and escape-characters normalized (eg. %2f -> %2F).
+            i += 1;
         }
     }
     // Return if no changes needed.
@@ -321,41 +894,126 @@ pub fn escape_pattern(path: &str) -> String {
     }
 
     let mut dest = String::with_capacity(num_to_escape * 2 + path.len() + 1);
-    chars = path.bytes();
-    loop {
-        match chars.next() {
-            Some(c) if c as char == '%' => {
-                // (a) Normalize %-escaped sequence (eg. %2f -> %2F).
-                match (
-                    chars.next().map(|c| c as char),
-                    chars.next().map(|c| c as char),
-                ) {
-                    (Some(c1), Some(c2)) if c1.is_digit(16) && c2.is_digit(16) => {
-                        dest.push(c as char);
-                        dest.push(c1.to_ascii_uppercase());
-                        dest.push(c2.to_ascii_uppercase());
-                    }
-                    _ => {}
-                }
-            }
-            Some(c) if c >= 0x80 => {
-                // (b) %-escape octets whose highest bit is set. These are outside the ASCII range.
-                dest.push('%');
-                dest.push(HEX_DIGITS[(c as usize >> 4) & 0xf]);
-                dest.push(HEX_DIGITS[c as usize & 0xf]);
-            }
-            Some(c) => {
-                // (c) Normal character, no modification needed.
-                dest.push(c as char);
-            }
-            None => {
-                break;
-            }
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if is_escape_sequence(bytes, i) {
+            // (a) Normalize %-escaped sequence (eg. %2f -> %2F).
+            dest.push('%');
+            dest.push(bytes[i + 1].to_ascii_uppercase() as char);
+            dest.push(bytes[i + 2].to_ascii_uppercase() as char);
+            i += 3;
+        } else if c >= 0x80 {
+            // (b) %-escape octets whose highest bit is set. These are outside the ASCII range.
+            dest.push('%');
+            dest.push(HEX_DIGITS[(c as usize >> 4) & 0xf]);
+            dest.push(HEX_DIGITS[c as usize & 0xf]);
+            i += 1;
+        } else {
+            // (c) Normal character, no modification needed.
+            dest.push(c as char);
+            i += 1;
         }
     }
     dest
 }
 
+/// Decodes the %-escape sequences of unreserved characters (ALPHA, DIGIT,
+/// '-', '.', '_' and '~'), which RFC 3986 considers equivalent to the
+/// characters themselves. Other escape sequences are kept as they are.
+///
+/// For example:
+/// ```txt
+///     /%70rivate ==> /private
+///     /a%2Fb%7e ==> /a%2Fb~
+/// ```
+/// If there is nothing to decode, the original string is returned unchanged.
+pub fn decode_unreserved(path: &str) -> Cow<'_, str> {
+    let bytes = path.as_bytes();
+    let decoded_at = |i: usize| {
+        if !is_escape_sequence(bytes, i) {
+            return None;
+        }
+        let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+        let c = u8::from_str_radix(hex, 16).ok()? as char;
+        if c.is_ascii_alphanumeric() || "-._~".contains(c) {
+            Some(c)
+        } else {
+            None
+        }
+    };
+    if !(0..bytes.len()).any(|i| decoded_at(i).is_some()) {
+        return Cow::Borrowed(path);
+    }
+
+    let mut dest = String::with_capacity(path.len());
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(c) = decoded_at(i) {
+            dest.push_str(&path[start..i]);
+            dest.push(c);
+            i += 3;
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    dest.push_str(&path[start..]);
+    Cow::Owned(dest)
+}
+
+/// Returns true if a valid %-escape sequence starts at `bytes[i]`.
+fn is_escape_sequence(bytes: &[u8], i: usize) -> bool {
+    bytes[i] == b'%'
+        && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+        && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit)
+}
+
+/// A '%' in a path pattern which doesn't start a valid %-escape sequence.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EncodingIssue {
+    /// Byte offset of the '%' in the pattern.
+    pub position: usize,
+    /// The '%' and the (at most two) characters following it.
+    pub sequence: String,
+}
+
+/// Reports the malformed %-escape sequences of a path pattern, such as `%zz` or
+/// a `%2` at the end. These are kept as they are by [escape_pattern], and
+/// thus only match URLs containing the very same malformed sequence.
+/// ```rust
+/// use robotstxt::parser::{validate_encoding, EncodingIssue};
+///
+/// assert!(validate_encoding("/a%2Fb%c3%a9").is_empty());
+/// assert_eq!(
+///     vec![
+///         EncodingIssue { position: 2, sequence: "%zz".to_string() },
+///         EncodingIssue { position: 7, sequence: "%2".to_string() },
+///     ],
+///     validate_encoding("/a%zz/b%2")
+/// );
+/// ```
+pub fn validate_encoding(pattern: &str) -> Vec<EncodingIssue> {
+    let bytes = pattern.as_bytes();
+    let mut issues = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_escape_sequence(bytes, i) {
+            i += 3;
+            continue;
+        }
+        if bytes[i] == b'%' {
+            issues.push(EncodingIssue {
+                position: i,
+                sequence: pattern[i..].chars().take(3).collect(),
+            });
+        }
+        i += 1;
+    }
+    issues
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(unused_variables)]
@@ -396,7 +1054,7 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_key_value<'a>() {
+    fn test_parse_key_value() {
         type Target<'a> = RobotsTxtParser<'a, FooHandler>;
         let negative = ("", "", false);
         let positive = ("User-agent", "Googlebot", true);
@@ -416,6 +1074,744 @@ mod tests {
             positive,
             Target::parse_key_value("User-agent\tGooglebot # 123")
         );
+
+        // Comments are stripped before splitting key and value, even without
+        // whitespace in front of them.
+        assert_eq!(
+            positive,
+            Target::parse_key_value("User-agent: Googlebot#note")
+        );
+        assert_eq!(
+            positive,
+            Target::parse_key_value("User-agent Googlebot#a b")
+        );
+
+        assert_eq!(("Disallow", "", true), Target::parse_key_value("Disallow:"));
+        assert_eq!(
+            ("Disallow", "", true),
+            Target::parse_key_value("Disallow:   ")
+        );
+        assert_eq!(
+            ("Disallow", "", true),
+            Target::parse_key_value("Disallow: \t ")
+        );
+    }
+
+    #[test]
+    fn test_decode_unreserved() {
+        assert_eq!("/private", decode_unreserved("/%70rivate"));
+        assert_eq!("/a-b_c.d~e", decode_unreserved("/a%2db%5Fc%2Ed%7e%65"));
+        assert_eq!("/a%2Fb%20%C3%A9", decode_unreserved("/a%2Fb%20%C3%A9"));
+        assert_eq!("/%7/%zz%", decode_unreserved("/%7/%zz%"));
+        assert!(matches!(decode_unreserved("/a%2Fb"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_parse_key_value_without_colon() {
+        type Target<'a> = RobotsTxtParser<'a, FooHandler>;
+        assert_eq!(
+            ("Disallow", "/path", true),
+            Target::parse_key_value("Disallow /path")
+        );
+        assert_eq!(
+            ("Disallow", "/a/b", true),
+            Target::parse_key_value("Disallow /a/b")
+        );
+        assert_eq!(
+            ("Allow", "/a/b*.html$", true),
+            Target::parse_key_value("Allow\t/a/b*.html$")
+        );
+        assert_eq!(
+            ("Sitemap", "/sitemap.xml", true),
+            Target::parse_key_value("Sitemap /sitemap.xml")
+        );
+        // The colon of an absolute URL is taken as the separator, as Google does.
+        assert_eq!(
+            ("Sitemap http", "//foo.bar/sitemap.xml", true),
+            Target::parse_key_value("Sitemap http://foo.bar/sitemap.xml")
+        );
+
+        // Runs of whitespace of any kind separate as well as a single one.
+        assert_eq!(
+            ("User-agent", "Googlebot", true),
+            Target::parse_key_value("User-agent\t\tGooglebot")
+        );
+        assert_eq!(
+            ("Disallow", "/a", true),
+            Target::parse_key_value("Disallow \t \t/a")
+        );
+        assert_eq!(
+            ("Disallow", "/a", true),
+            Target::parse_key_value(" \tDisallow  /a\t ")
+        );
+        assert_eq!(
+            ("Disallow", "/a", true),
+            Target::parse_key_value("Disallow\t\t/a  # comment")
+        );
+
+        // More than two sequences of non-whitespace characters.
+        assert_eq!(("", "", false), Target::parse_key_value("Disallow /a b"));
+        assert_eq!(
+            ("", "", false),
+            Target::parse_key_value("Disallow\t\t/a\t\tb")
+        );
+        assert_eq!(("", "", false), Target::parse_key_value("Allow\t/a /b"));
+        // A single sequence has no value.
+        assert_eq!(("", "", false), Target::parse_key_value("Disallow"));
+    }
+
+    #[test]
+    fn test_equals_separator() {
+        type Target<'a> = RobotsTxtParser<'a, FooHandler>;
+        assert_eq!(
+            ("Disallow", "/path", true),
+            Target::parse_key_value_lenient("Disallow = /path")
+        );
+        assert_eq!(
+            ("User-agent", "Googlebot", true),
+            Target::parse_key_value_lenient("User-agent=Googlebot")
+        );
+        // A colon still takes precedence, and an `=` in a colon-less value
+        // doesn't split it.
+        assert_eq!(
+            ("Disallow", "/a?b=c", true),
+            Target::parse_key_value_lenient("Disallow: /a?b=c")
+        );
+        assert_eq!(
+            ("Disallow", "/a?b=c", true),
+            Target::parse_key_value_lenient("Disallow /a?b=c")
+        );
+        assert_eq!(("", "", false), Target::parse_key_value("Disallow = /path"));
+
+        let robotstxt = "User-agent = FooBot\nDisallow = /path\n";
+        let mut collector = crate::collector::GroupCollector::default();
+        RobotsTxtParser::new(robotstxt, &mut collector)
+            .with_equals_separator(true)
+            .parse();
+        let groups = collector.groups();
+        assert_eq!(1, groups.len());
+        assert_eq!(vec!["FooBot"], groups[0].user_agents);
+        assert_eq!(
+            crate::collector::RuleKind::Disallow,
+            groups[0].rules[0].kind
+        );
+        assert_eq!("/path", groups[0].rules[0].pattern);
+
+        crate::parse_robotstxt(robotstxt, &mut collector);
+        assert!(collector.groups().is_empty());
+    }
+
+    #[test]
+    fn test_overlong_lines_are_truncated() {
+        use crate::collector::GroupCollector;
+
+        // A single line without line break, way past the maximum line length.
+        let path = "/a".repeat(25 * 1024);
+        let robotstxt = format!("user-agent: FooBot\ndisallow: {}", path);
+        let mut collector = GroupCollector::default();
+        crate::parse_robotstxt(&robotstxt, &mut collector);
+        let pattern = &collector.groups()[0].rules[0].pattern;
+        assert_eq!(
+            LineSplitter::MAX_LINE_LEN - 1,
+            "disallow: ".len() + pattern.len()
+        );
+        assert!(path.starts_with(pattern.as_str()));
+
+        // The overflow of a line is dropped up to the line break, even if it
+        // holds multi-byte characters, and parsing goes on with the next line.
+        let robotstxt = format!(
+            "user-agent: FooBot\ndisallow: /{}\nallow: /b\n",
+            "é".repeat(LineSplitter::MAX_LINE_LEN)
+        );
+        crate::parse_robotstxt(&robotstxt, &mut collector);
+        let rules = &collector.groups()[0].rules;
+        assert_eq!(2, rules.len());
+        assert!(rules[0].pattern.starts_with("/%C3%A9%C3%A9"));
+        assert_eq!((3, "/b"), (rules[1].line, rules[1].pattern.as_str()));
+    }
+
+    #[test]
+    fn test_max_groups() {
+        use crate::collector::GroupCollector;
+
+        let mut robotstxt = String::new();
+        for i in 0..1000 {
+            robotstxt.push_str(&format!(
+                "user-agent: Bot{}\nuser-agent: *\ndisallow: /{}\n",
+                i, i
+            ));
+        }
+        robotstxt.push_str("sitemap: http://foo.com/sitemap.xml\n");
+
+        let mut collector = GroupCollector::default();
+        RobotsTxtParser::new(&robotstxt, &mut collector)
+            .with_max_groups(10)
+            .parse();
+        let groups = collector.groups();
+        assert_eq!(10, groups.len());
+        assert_eq!(vec!["Bot9", "*"], groups[9].user_agents);
+        assert_eq!(1, groups[9].rules.len());
+        assert_eq!(vec!["http://foo.com/sitemap.xml"], collector.sitemaps());
+
+        crate::parse_robotstxt(&robotstxt, &mut collector);
+        assert_eq!(1000, collector.groups().len());
+    }
+
+    #[test]
+    fn test_group_callbacks() {
+        #[derive(Default)]
+        struct GroupRecorder(Vec<String>);
+
+        impl RobotsParseHandler for GroupRecorder {
+            fn handle_robots_start(&mut self) {}
+            fn handle_robots_end(&mut self) {
+                self.0.push("end".into());
+            }
+            fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+                self.0.push(format!("{} {}", line_num, user_agent));
+            }
+            fn handle_group_start(&mut self, line_num: u32) {
+                self.0.push(format!("{} group start", line_num));
+            }
+            fn handle_group_end(&mut self) {
+                self.0.push("group end".into());
+            }
+            fn handle_allow(&mut self, line_num: u32, value: &str) {}
+            fn handle_disallow(&mut self, line_num: u32, value: &str) {}
+            fn handle_sitemap(&mut self, line_num: u32, value: &str) {}
+            fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {}
+        }
+
+        let robotstxt = "disallow: /before\n\
+        user-agent: FooBot\n\
+        # Comments and blank lines don't end a group.\n\
+        \n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        user-agent: *\n\
+        sitemap: http://foo.com/sitemap.xml\n\
+        user-agent: BazBot\n\
+        crawl-delay: 1\n\
+        user-agent: QuxBot\n";
+        let parse = |max_groups| {
+            let mut recorder = GroupRecorder::default();
+            let mut parser = RobotsTxtParser::new(robotstxt, &mut recorder);
+            if let Some(max_groups) = max_groups {
+                parser = parser.with_max_groups(max_groups);
+            }
+            parser.parse();
+            recorder.0
+        };
+        assert_eq!(
+            vec![
+                "2 group start",
+                "2 FooBot",
+                "5 BarBot",
+                "group end",
+                "7 group start",
+                "7 *",
+                "group end",
+                "9 group start",
+                "9 BazBot",
+                "group end",
+                "11 group start",
+                "11 QuxBot",
+                "group end",
+                "end",
+            ],
+            parse(None)
+        );
+        assert_eq!(
+            vec!["2 group start", "2 FooBot", "5 BarBot", "group end", "end"],
+            parse(Some(1))
+        );
+
+        let mut recorder = GroupRecorder::default();
+        RobotsTxtParser::for_chunks(&mut recorder)
+            .parse_chunks(vec!["user-", "agent: *\nallow: /"]);
+        assert_eq!(vec!["1 group start", "1 *", "group end", "end"], recorder.0);
+    }
+
+    #[test]
+    fn test_lone_carriage_return_warnings() {
+        #[derive(Default)]
+        struct Warnings(Vec<u32>);
+
+        impl RobotsParseHandler for Warnings {
+            fn handle_robots_start(&mut self) {}
+            fn handle_robots_end(&mut self) {}
+            fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {}
+            fn handle_allow(&mut self, line_num: u32, value: &str) {}
+            fn handle_disallow(&mut self, line_num: u32, value: &str) {}
+            fn handle_sitemap(&mut self, line_num: u32, value: &str) {}
+            fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {}
+            fn handle_lone_carriage_return(&mut self, line_num: u32) {
+                self.0.push(line_num);
+            }
+        }
+
+        let robotstxt = "user-agent: FooBot\r\n\
+        disallow: /a\r\
+        allow: /b\n\
+        \r\
+        \r\n\
+        disallow: /c\r";
+        let mut warnings = Warnings::default();
+        RobotsTxtParser::new(robotstxt, &mut warnings)
+            .with_lone_carriage_return_warnings(true)
+            .parse();
+        assert_eq!(vec![2, 4, 6], warnings.0);
+
+        let mut warnings = Warnings::default();
+        crate::parse_robotstxt(robotstxt, &mut warnings);
+        assert!(warnings.0.is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_lines() {
+        assert_eq!(vec![(1, "")], tokenize_lines("").collect::<Vec<_>>());
+        assert_eq!(
+            vec![(1, "a"), (2, ""), (3, "b"), (4, "")],
+            tokenize_lines("\u{FEFF}a\n\r\nb\r\n").collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(1, "a"), (2, ""), (3, "b")],
+            tokenize_lines("a\r\rb").collect::<Vec<_>>()
+        );
+        // Only a byte order mark starting the body is skipped.
+        assert_eq!(
+            vec![(1, "a"), (2, "\u{FEFF}b")],
+            tokenize_lines("\u{FEFF}a\n\u{FEFF}b").collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(1, "\u{FEFF}a")],
+            tokenize_lines("\u{FEFF}\u{FEFF}a").collect::<Vec<_>>()
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl RobotsParseHandler for Recorder {
+        fn handle_robots_start(&mut self) {
+            self.0.push("start".into());
+        }
+        fn handle_robots_end(&mut self) {
+            self.0.push("end".into());
+        }
+        fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+            self.0
+                .push(format!("{} user-agent {}", line_num, user_agent));
+        }
+        fn handle_allow(&mut self, line_num: u32, value: &str) {
+            self.0.push(format!("{} allow {}", line_num, value));
+        }
+        fn handle_disallow(&mut self, line_num: u32, value: &str) {
+            self.0.push(format!("{} disallow {}", line_num, value));
+        }
+        fn handle_sitemap(&mut self, line_num: u32, value: &str) {
+            self.0.push(format!("{} sitemap {}", line_num, value));
+        }
+        fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {
+            self.0.push(format!("{} {} {}", line_num, action, value));
+        }
+        fn handle_parse_error(&mut self, line_num: u32, raw_line: &str) {
+            self.0.push(format!("{} error {}", line_num, raw_line));
+        }
+        fn handle_comment(&mut self, line_num: u32, comment: &str) {
+            self.0.push(format!("{} comment {}", line_num, comment));
+        }
+        fn handle_lone_carriage_return(&mut self, line_num: u32) {
+            self.0.push(format!("{} lone cr", line_num));
+        }
+        fn handle_custom_directive(&mut self, line_num: u32, key: &str, value: &str) {
+            self.0
+                .push(format!("{} custom {} {}", line_num, key, value));
+        }
+        fn handle_raw_value(&mut self, line_num: u32, raw_value: &str, escaped_value: &str) {
+            if raw_value != escaped_value {
+                self.0
+                    .push(format!("{} raw {} {}", line_num, raw_value, escaped_value));
+            }
+        }
+        fn handle_typo_correction(
+            &mut self,
+            line_num: u32,
+            original_key: &str,
+            corrected_type: ParseKeyType,
+        ) {
+            let corrected = match corrected_type {
+                ParseKeyType::UserAgent => "user-agent",
+                ParseKeyType::Disallow => "disallow",
+                _ => "other",
+            };
+            self.0
+                .push(format!("{} typo {} {}", line_num, original_key, corrected));
+        }
+        fn handle_progress(&mut self, bytes_processed: usize, total_bytes: usize) {
+            self.0
+                .push(format!("progress {}/{}", bytes_processed, total_bytes));
+        }
+    }
+
+    fn record(robotstxt: &str) -> Vec<String> {
+        let mut recorder = Recorder::default();
+        RobotsTxtParser::new(robotstxt, &mut recorder)
+            .with_lone_carriage_return_warnings(true)
+            .parse();
+        recorder.0
+    }
+
+    fn record_chunks(chunks: &[&str]) -> Vec<String> {
+        let mut recorder = Recorder::default();
+        RobotsTxtParser::for_chunks(&mut recorder)
+            .with_lone_carriage_return_warnings(true)
+            .parse_chunks(chunks.iter().copied());
+        recorder.0
+    }
+
+    #[test]
+    fn test_parse_chunks() {
+        let robotstxt = "\u{FEFF}User-agent: FooBot # ours\r\n\
+        Disallow: /café\r\
+        \r\n\
+        Allow /a\n\
+        foo bar baz\r\r\
+        Sitemap: http://foo.com/sitemap.xml\r";
+        let expected = record(robotstxt);
+        assert_eq!(expected, record_chunks(&[robotstxt]));
+        for (split, _) in robotstxt.char_indices() {
+            let (first, second) = robotstxt.split_at(split);
+            assert_eq!(expected, record_chunks(&[first, second]), "{}", split);
+            assert_eq!(
+                expected,
+                record_chunks(&[first, "", second, ""]),
+                "{}",
+                split
+            );
+        }
+        let chunks: Vec<String> = robotstxt.chars().map(String::from).collect();
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        assert_eq!(expected, record_chunks(&chunks));
+
+        assert_eq!(record(""), record_chunks(&[]));
+        assert_eq!(record("a\n"), record_chunks(&["a", "\n"]));
+    }
+
+    #[test]
+    fn test_parse_chunks_overlong_line() {
+        let robotstxt = format!(
+            "user-agent: FooBot\ndisallow: /{}\nallow: /b",
+            "é".repeat(LineSplitter::MAX_LINE_LEN)
+        );
+        let expected = record(&robotstxt);
+        for split in &[20, 100, 10_000, 16_664, 16_666, 30_000, robotstxt.len() - 3] {
+            let (first, second) = robotstxt.split_at(*split);
+            assert_eq!(expected, record_chunks(&[first, second]), "{}", split);
+        }
+    }
+
+    #[test]
+    fn test_progress_interval() {
+        let robotstxt = "user-agent: *\ndisallow: /a\nallow: /b\n";
+        let mut recorder = Recorder::default();
+        RobotsTxtParser::new(robotstxt, &mut recorder)
+            .with_progress_interval(2)
+            .parse();
+        assert_eq!(
+            vec![
+                "start",
+                "1 user-agent *",
+                "2 disallow /a",
+                "progress 27/37",
+                "3 allow /b",
+                "progress 37/37",
+                "end",
+            ],
+            recorder.0
+        );
+
+        // Nothing is reported by default.
+        assert!(record(robotstxt).iter().all(|s| !s.starts_with("progress")));
+    }
+
+    #[test]
+    fn test_typo_correction() {
+        assert_eq!(
+            vec![
+                "start",
+                "1 typo useragent user-agent",
+                "1 user-agent FooBot",
+                "2 typo Disalow disallow",
+                "2 disallow /a",
+                "3 disallow /b",
+                "4 allow /c",
+                "end",
+            ],
+            record("useragent: FooBot\nDisalow: /a\ndisallow: /b\nallow: /c")
+        );
+
+        let mut key = ParsedRobotsKey::default();
+        key.parse("dissallow");
+        assert!(key.is_typo_corrected());
+        key.parse("disallow");
+        assert!(!key.is_typo_corrected());
+        key.parse("disalowed");
+        assert!(key.is_typo_corrected());
+        key.parse("foo");
+        assert!(!key.is_typo_corrected());
+    }
+
+    #[test]
+    fn test_without_google_extensions() {
+        let robotstxt = "\u{FEFF}user-agent: FooBot\n\
+        useragent: BarBot\n\
+        disalow: /a\n\
+        disallow /b\n\
+        disallow: /c\n";
+        let expected = vec![
+            "start",
+            "1 \u{FEFF}user-agent FooBot",
+            "2 useragent BarBot",
+            "3 disalow /a",
+            "4 error disallow /b",
+            "5 disallow /c",
+            "end",
+        ];
+        let mut recorder = Recorder::default();
+        RobotsTxtParser::new(robotstxt, &mut recorder)
+            .with_google_extensions(false)
+            .parse();
+        assert_eq!(expected, recorder.0);
+
+        let mut recorder = Recorder::default();
+        RobotsTxtParser::new(robotstxt, &mut recorder)
+            .with_google_extensions(false)
+            .parse_chunks(robotstxt.split_inclusive('\n'));
+        assert_eq!(expected, recorder.0);
+    }
+
+    #[test]
+    fn test_raw_values() {
+        assert_eq!(
+            vec![
+                "start",
+                "1 user-agent FooBot",
+                "2 raw /caf\u{e9} /caf%C3%A9",
+                "2 disallow /caf%C3%A9",
+                "3 allow /caf%C3%A9",
+                "4 raw /%aa /%AA",
+                "4 allow /%AA",
+                "5 sitemap /caf\u{e9}.xml",
+                "6 x-foo x%C3%A9",
+                "end",
+            ],
+            record(
+                "user-agent: FooBot\n\
+                disallow: /caf\u{e9}\n\
+                allow: /caf%C3%A9\n\
+                allow: /%aa\n\
+                sitemap: /caf\u{e9}.xml\n\
+                x-foo: x\u{e9}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_detect_line_endings() {
+        let mixed_file = "User-Agent: foo\n\
+        Allow: /some/path\r\n\
+        User-Agent: bar\n\
+        \r\n\
+        \n\
+        Disallow: /";
+        let report = detect_line_endings(mixed_file);
+        assert_eq!(
+            LineEndingReport {
+                lf: 3,
+                cr: 0,
+                crlf: 2
+            },
+            report
+        );
+        assert!(report.is_mixed());
+
+        let mac_file = "User-Agent: foo\r\
+        Allow: /some/path\r\
+        \r\
+        Disallow: /\r";
+        let report = detect_line_endings(mac_file);
+        assert_eq!(
+            LineEndingReport {
+                lf: 0,
+                cr: 4,
+                crlf: 0
+            },
+            report
+        );
+        assert!(!report.is_mixed());
+
+        // "\n\r" is a line feed followed by a lone carriage return.
+        assert_eq!(
+            LineEndingReport {
+                lf: 1,
+                cr: 1,
+                crlf: 1
+            },
+            detect_line_endings("a\n\rb\r\n")
+        );
+        assert_eq!(
+            LineEndingReport::default(),
+            detect_line_endings("Disallow: /")
+        );
+        assert!(!LineEndingReport::default().is_mixed());
+    }
+
+    #[test]
+    fn test_line_continuations() {
+        let robotstxt = "user-agent: Foo\\\n\
+        Bot\n\
+        disallow: /a\\\n\
+        b\\\r\n\
+        c\n\
+        allow: /d\\";
+        let record_continued = |parse: &dyn Fn(&mut Recorder)| {
+            let mut recorder = Recorder::default();
+            parse(&mut recorder);
+            recorder.0
+        };
+        let expected = vec![
+            "start",
+            "1 user-agent FooBot",
+            "3 disallow /abc",
+            "6 allow /d",
+            "end",
+        ];
+        assert_eq!(
+            expected,
+            record_continued(&|recorder| RobotsTxtParser::new(robotstxt, recorder)
+                .with_line_continuations(true)
+                .parse())
+        );
+        assert_eq!(
+            expected,
+            record_continued(&|recorder| RobotsTxtParser::new(robotstxt, recorder)
+                .with_line_continuations(true)
+                .parse_chunks(robotstxt.split_inclusive('b')))
+        );
+
+        // Backslashes are kept as they are by default.
+        assert_eq!(
+            vec![
+                "start",
+                "1 user-agent Foo\\",
+                "2 error Bot",
+                "3 disallow /a\\",
+                "4 error b\\",
+                "5 error c",
+                "6 allow /d\\",
+                "end",
+            ],
+            record(robotstxt)
+        );
+    }
+
+    #[test]
+    fn test_custom_keys() {
+        let robotstxt = "user-agent: *\n\
+        X-Robots-Policy: noai\n\
+        x-robots-policy-2: x\n\
+        crawl-delay: 1\n";
+        let mut recorder = Recorder::default();
+        RobotsTxtParser::new(robotstxt, &mut recorder)
+            .with_custom_key("x-robots-policy")
+            .with_custom_key("Crawl-Delay")
+            .parse();
+        assert_eq!(
+            vec![
+                "start",
+                "1 user-agent *",
+                "2 custom X-Robots-Policy noai",
+                "3 x-robots-policy-2 x",
+                "4 custom crawl-delay 1",
+                "end",
+            ],
+            recorder.0
+        );
+
+        // Handlers not interested in custom directives see unknown actions.
+        #[derive(Default)]
+        struct UnknownActions(Vec<String>);
+        impl RobotsParseHandler for UnknownActions {
+            fn handle_robots_start(&mut self) {}
+            fn handle_robots_end(&mut self) {}
+            fn handle_user_agent(&mut self, _line_num: u32, _user_agent: &str) {}
+            fn handle_allow(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_disallow(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_sitemap(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_unknown_action(&mut self, _line_num: u32, action: &str, _value: &str) {
+                self.0.push(action.to_string());
+            }
+        }
+        let mut unknown_actions = UnknownActions::default();
+        RobotsTxtParser::new(robotstxt, &mut unknown_actions)
+            .with_custom_key("x-robots-policy")
+            .parse();
+        assert_eq!(
+            vec!["X-Robots-Policy", "x-robots-policy-2", "crawl-delay"],
+            unknown_actions.0
+        );
+
+        let mut key = ParsedRobotsKey::default();
+        key.parse_with_custom_keys("ALLOW-ALL", &["allow-all".to_string()]);
+        assert_eq!(&ParseKeyType::Custom, key.get_type());
+        assert_eq!("ALLOW-ALL", key.get_unknown_text());
+        key.parse_with_custom_keys("allow", &["allow-all".to_string()]);
+        assert_eq!(&ParseKeyType::Allow, key.get_type());
+    }
+
+    #[test]
+    fn test_quote_stripping() {
+        use crate::DefaultMatcher;
+
+        let robotstxt = "user-agent: \"FooBot\"\n\
+        disallow: '/a'\n\
+        disallow: \"/b\n\
+        sitemap: \"http://foo.com/sitemap.xml\"\n";
+        let check = |strip_quotes: bool, url: &str| {
+            let mut matcher = DefaultMatcher::default().with_quote_stripping(strip_quotes);
+            matcher.one_agent_allowed_by_robots(robotstxt, "FooBot", url)
+        };
+        // The quoted agent only names FooBot in lenient mode.
+        assert!(check(false, "http://foo.com/a"));
+        assert!(!check(true, "http://foo.com/a"));
+        // Unbalanced quotes are kept.
+        assert!(check(true, "http://foo.com/b"));
+        assert!(check(false, "http://foo.com/b"));
+
+        assert_eq!("a b", strip_quotes("\" a b \""));
+        assert_eq!("\"a'", strip_quotes("\"a'"));
+        assert_eq!("", strip_quotes("''"));
+        assert_eq!("\"", strip_quotes("\""));
+    }
+
+    #[test]
+    fn test_parsed_robots_key() {
+        let line = String::from("Crawl-delay: 10");
+        let mut key = ParsedRobotsKey::default();
+        key.parse(&line[..11]);
+        assert_eq!(&ParseKeyType::Unknown, key.get_type());
+        // The unknown text borrows from the line, no copy is made.
+        assert_eq!(line.as_ptr(), key.get_unknown_text().as_ptr());
+        assert_eq!("Crawl-delay", key.get_unknown_text());
+
+        key.parse("disalow");
+        assert_eq!(&ParseKeyType::Disallow, key.get_type());
+
+        // Keys are matched by prefix, so trailing whitespace left before a
+        // colon doesn't matter.
+        key.parse("SITEMAP \t");
+        assert_eq!(&ParseKeyType::Sitemap, key.get_type());
     }
 
     #[test]
@@ -429,5 +1825,31 @@ mod tests {
         assert_eq!("%AA", &escape_pattern("%aA"));
         assert_eq!("/Sanjos%C3%A9Sellers", &escape_pattern("/SanjoséSellers"));
         assert_eq!("%C3%A1", &escape_pattern("á"));
+        // Malformed escape sequences are kept as they are.
+        assert_eq!("/a%zz%2", &escape_pattern("/a%zz%2"));
+        assert_eq!("%%AA%", &escape_pattern("%%aa%"));
+        assert_eq!("%%C3%A9", &escape_pattern("%é"));
+        // '+' is a literal character, it doesn't stand for a space.
+        assert_eq!("/a?b+c", &escape_pattern("/a?b+c"));
+        assert_eq!("/a%20b+c", &escape_pattern("/a%20b+c"));
+    }
+
+    #[test]
+    fn test_validate_encoding() {
+        assert!(validate_encoding("").is_empty());
+        assert!(validate_encoding("/a/b%20c%aA").is_empty());
+        let positions = |pattern| {
+            validate_encoding(pattern)
+                .into_iter()
+                .map(|issue| (issue.position, issue.sequence))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![(0, "%".to_string())], positions("%"));
+        assert_eq!(vec![(1, "%g1".to_string())], positions("/%g1%41"));
+        assert_eq!(
+            vec![(0, "%%4".to_string()), (1, "%4".to_string())],
+            positions("%%4")
+        );
+        assert_eq!(vec![(1, "%é".to_string())], positions("/%é"));
     }
 }
@@ -0,0 +1,460 @@
+// Copyright 2020 Folyd
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use crate::collector::{Group, GroupCollector, RuleKind};
+use crate::{get_path_params_query, parse_robotstxt, DefaultMatcher, RobotsParseHandler};
+
+/// A directive of robots.txt, as emitted by the parser.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Directive {
+    UserAgent(String),
+    Allow(String),
+    Disallow(String),
+    Sitemap(String),
+    Unknown(String, String),
+}
+
+/// A parsed robots.txt, ready to answer any number of checks.
+///
+/// The robots.txt is parsed only once: the directives found are kept, already
+/// split, typo-corrected and %-escaped, and fed to a [DefaultMatcher] on every
+/// check. There are no lifetimes to care about, the parsed robots.txt owns its
+/// data.
+/// ```rust
+/// use robotstxt::Robots;
+///
+/// let robots = Robots::parse(
+///     "user-agent: FooBot\n\
+///      disallow: /private\n\
+///      sitemap: https://foo.com/sitemap.xml\n",
+/// );
+/// assert!(robots.allowed("FooBot", "https://foo.com/"));
+/// assert!(!robots.allowed("FooBot", "https://foo.com/private"));
+/// assert_eq!(vec!["https://foo.com/sitemap.xml"], robots.sitemaps());
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Robots {
+    directives: Vec<(u32, Directive)>,
+    /// Matchable user-agent tokens having groups of their own, lowercased, and
+    /// whether those groups block everything.
+    blanket_disallowed_agents: Vec<(String, bool)>,
+    /// True if the global groups block everything.
+    blanket_disallowed_global: bool,
+}
+
+/// Whether groups hold a `Disallow: /` and any allow rule.
+#[derive(Default, Clone, Copy)]
+struct BlanketRules {
+    disallow_root: bool,
+    allow: bool,
+}
+
+impl BlanketRules {
+    fn add(&mut self, group: &Group) {
+        for rule in &group.rules {
+            match rule.kind {
+                RuleKind::Disallow => self.disallow_root |= rule.pattern == "/",
+                // An empty allow never wins against `Disallow: /`.
+                RuleKind::Allow => self.allow |= !rule.pattern.is_empty(),
+            }
+        }
+    }
+
+    fn blocks_everything(self) -> bool {
+        self.disallow_root && !self.allow
+    }
+}
+
+impl Robots {
+    /// Parses the body of a robots.txt.
+    pub fn parse(robots_body: &str) -> Robots {
+        let mut robots = Robots::default();
+        parse_robotstxt(robots_body, &mut DirectiveCollector(&mut robots));
+        robots.find_blanket_disallows();
+        robots
+    }
+
+    /// Finds the user-agents for which a `Disallow: /` with no allow rule blocks
+    /// every URL, to answer their checks without any matching.
+    fn find_blanket_disallows(&mut self) {
+        let mut collector = GroupCollector::default();
+        self.replay(&mut collector);
+
+        let mut global = BlanketRules::default();
+        let mut agents: Vec<(String, BlanketRules)> = vec![];
+        for group in collector.groups() {
+            if group.is_global() {
+                global.add(group);
+            }
+            for user_agent in &group.user_agents {
+                if DefaultMatcher::is_global_agent(user_agent) {
+                    continue;
+                }
+                let token = DefaultMatcher::extract_user_agent(user_agent).to_ascii_lowercase();
+                let index = match agents.iter().position(|(agent, _)| *agent == token) {
+                    Some(index) => index,
+                    None => {
+                        agents.push((token, BlanketRules::default()));
+                        agents.len() - 1
+                    }
+                };
+                agents[index].1.add(group);
+            }
+        }
+        self.blanket_disallowed_global = global.blocks_everything();
+        self.blanket_disallowed_agents = agents
+            .into_iter()
+            .map(|(agent, rules)| (agent, rules.blocks_everything()))
+            .collect();
+    }
+
+    /// Returns true if every URL is disallowed for `user_agent`: the groups it
+    /// obeys hold a `Disallow: /` and no allow rule.
+    fn blanket_disallowed(&self, user_agent: &str) -> bool {
+        self.blanket_disallowed_agents
+            .iter()
+            .find(|(agent, _)| agent.eq_ignore_ascii_case(user_agent))
+            .map_or(self.blanket_disallowed_global, |(_, blocked)| *blocked)
+    }
+
+    /// Returns true if 'url' is allowed to be fetched by 'user_agent'. 'url' must
+    /// be %-encoded according to RFC3986.
+    ///
+    /// If the groups `user_agent` obeys have a `Disallow: /` and no allow rule,
+    /// false is returned right away, without matching.
+    pub fn allowed(&self, user_agent: &str, url: &str) -> bool {
+        if self.blanket_disallowed(user_agent) {
+            return false;
+        }
+        let mut matcher = DefaultMatcher::default();
+        matcher.init_user_agents_and_path(vec![user_agent], get_path_params_query(url));
+        self.replay(&mut matcher);
+        !matcher.disallow()
+    }
+
+    /// Returns true if 'url' is allowed to be fetched by any of 'user_agents',
+    /// with the semantics of [allowed_by_robots](crate::matcher::RobotsMatcher::allowed_by_robots()).
+    #[cfg(feature = "cache")]
+    pub(crate) fn allowed_by_any(&self, user_agents: &[&str], url: &str) -> bool {
+        if let [user_agent] = user_agents {
+            return self.allowed(user_agent, url);
+        }
+        let mut matcher = DefaultMatcher::default();
+        matcher.init_user_agents_and_path(user_agents.to_vec(), get_path_params_query(url));
+        self.replay(&mut matcher);
+        !matcher.disallow()
+    }
+
+    /// Returns the user-agent groups in the order they appear in the robots.txt,
+    /// see [GroupCollector].
+    /// ```rust
+    /// use robotstxt::Robots;
+    /// use robotstxt::collector::{Group, Rule, RuleKind};
+    ///
+    /// let robots = Robots::parse("user-agent: FooBot\ndisallow: /a\n");
+    /// assert_eq!(
+    ///     vec![Group {
+    ///         user_agents: vec!["FooBot".into()],
+    ///         rules: vec![Rule { kind: RuleKind::Disallow, pattern: "/a".into(), line: 2 }],
+    ///         start_line: 1,
+    ///         end_line: 2,
+    ///     }],
+    ///     robots.groups()
+    /// );
+    /// ```
+    pub fn groups(&self) -> Vec<Group> {
+        let mut collector = GroupCollector::default();
+        self.replay(&mut collector);
+        collector.groups().to_vec()
+    }
+
+    /// Returns the sitemap values in the order they appear in the robots.txt.
+    pub fn sitemaps(&self) -> Vec<&str> {
+        self.directives
+            .iter()
+            .filter_map(|(_, directive)| match directive {
+                Directive::Sitemap(value) => Some(value.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Emits the parse callbacks of the robots.txt to `handler`, as
+    /// [parse_robotstxt] would do for the original body.
+    pub(crate) fn replay(&self, handler: &mut impl RobotsParseHandler) {
+        handler.handle_robots_start();
+        let mut in_user_agents = false;
+        let mut in_group = false;
+        for (line_num, directive) in &self.directives {
+            let line_num = *line_num;
+            let is_user_agent = matches!(directive, Directive::UserAgent(_));
+            if is_user_agent && !in_user_agents {
+                if in_group {
+                    handler.handle_group_end();
+                }
+                in_group = true;
+                handler.handle_group_start(line_num);
+            }
+            in_user_agents = is_user_agent;
+            match directive {
+                Directive::UserAgent(value) => handler.handle_user_agent(line_num, value),
+                Directive::Allow(value) => handler.handle_allow(line_num, value),
+                Directive::Disallow(value) => handler.handle_disallow(line_num, value),
+                Directive::Sitemap(value) => handler.handle_sitemap(line_num, value),
+                Directive::Unknown(action, value) => {
+                    handler.handle_unknown_action(line_num, action, value)
+                }
+            }
+            if handler.stop_parsing() {
+                break;
+            }
+        }
+        if in_group {
+            handler.handle_group_end();
+        }
+        handler.handle_robots_end();
+    }
+}
+
+impl From<&str> for Robots {
+    fn from(robots_body: &str) -> Self {
+        Robots::parse(robots_body)
+    }
+}
+
+struct DirectiveCollector<'a>(&'a mut Robots);
+
+impl DirectiveCollector<'_> {
+    fn push(&mut self, line_num: u32, directive: Directive) {
+        self.0.directives.push((line_num, directive));
+    }
+}
+
+impl RobotsParseHandler for DirectiveCollector<'_> {
+    fn handle_robots_start(&mut self) {
+        self.0.directives.clear();
+    }
+
+    fn handle_robots_end(&mut self) {}
+
+    fn handle_user_agent(&mut self, line_num: u32, user_agent: &str) {
+        self.push(line_num, Directive::UserAgent(user_agent.to_string()));
+    }
+
+    fn handle_allow(&mut self, line_num: u32, value: &str) {
+        self.push(line_num, Directive::Allow(value.to_string()));
+    }
+
+    fn handle_disallow(&mut self, line_num: u32, value: &str) {
+        self.push(line_num, Directive::Disallow(value.to_string()));
+    }
+
+    fn handle_sitemap(&mut self, line_num: u32, value: &str) {
+        self.push(line_num, Directive::Sitemap(value.to_string()));
+    }
+
+    fn handle_unknown_action(&mut self, line_num: u32, action: &str, value: &str) {
+        self.push(
+            line_num,
+            Directive::Unknown(action.to_string(), value.to_string()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::robots::*;
+
+    #[test]
+    fn test_same_verdicts_as_matcher() {
+        let robots_body = "allow: /ignored\n\
+        user-agent: FooBot\n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        allow: /public/index.html\n\
+        crawl-delay: 1\n\
+        user-agent: *\n\
+        disallow: /private\n\
+        \n\
+        user-agent: BazBot\n\
+        disallow: /caf%c3%a9\n\
+        sitemap: http://foo.com/sitemap.xml\n\
+        user-agent: FooBot\n\
+        allow: /fish\n";
+        let robots = Robots::from(robots_body);
+        let mut matcher = DefaultMatcher::default();
+        for user_agent in &["FooBot", "BarBot", "BazBot", "QuxBot"] {
+            for url in &[
+                "http://foo.com/",
+                "http://foo.com/ignored",
+                "http://foo.com/public/",
+                "http://foo.com/fish",
+                "http://foo.com/private",
+                "http://foo.com/caf%C3%A9",
+            ] {
+                assert_eq!(
+                    matcher.one_agent_allowed_by_robots(robots_body, user_agent, url),
+                    robots.allowed(user_agent, url),
+                    "{} {}",
+                    user_agent,
+                    url
+                );
+            }
+        }
+        assert_eq!(vec!["http://foo.com/sitemap.xml"], robots.sitemaps());
+    }
+
+    #[test]
+    fn test_replay_group_boundaries() {
+        #[derive(Default)]
+        struct Boundaries(Vec<(bool, u32)>);
+
+        impl RobotsParseHandler for Boundaries {
+            fn handle_robots_start(&mut self) {}
+            fn handle_robots_end(&mut self) {}
+            fn handle_user_agent(&mut self, _line_num: u32, _user_agent: &str) {}
+            fn handle_group_start(&mut self, line_num: u32) {
+                self.0.push((true, line_num));
+            }
+            fn handle_group_end(&mut self) {
+                self.0.push((false, 0));
+            }
+            fn handle_allow(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_disallow(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_sitemap(&mut self, _line_num: u32, _value: &str) {}
+            fn handle_unknown_action(&mut self, _line_num: u32, _action: &str, _value: &str) {}
+        }
+
+        let robots_body = "allow: /ignored\n\
+        user-agent: FooBot\n\
+        # comment\n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        user-agent: *\n\
+        sitemap: http://foo.com/sitemap.xml\n\
+        user-agent: BazBot\n";
+        let mut parsed = Boundaries::default();
+        crate::parse_robotstxt(robots_body, &mut parsed);
+        let mut replayed = Boundaries::default();
+        Robots::parse(robots_body).replay(&mut replayed);
+        assert_eq!(parsed.0, replayed.0);
+        assert_eq!(6, parsed.0.len());
+    }
+
+    #[test]
+    fn test_empty_robots() {
+        let robots = Robots::parse("");
+        assert!(robots.allowed("FooBot", "http://foo.com/"));
+        assert!(robots.sitemaps().is_empty());
+        assert_eq!(Robots::default(), robots);
+    }
+
+    #[test]
+    fn test_groups_snapshot() {
+        use crate::collector::Rule;
+
+        let robots = Robots::parse(
+            "user-agent: FooBot\n\
+            user-agent: BarBot\n\
+            disallow: /private\n\
+            allow: /private/public\n\
+            crawl-delay: 2\n\
+            \n\
+            user-agent: *\n\
+            disallow: /tmp\n",
+        );
+        assert_eq!(
+            vec![
+                Group {
+                    user_agents: vec!["FooBot".into(), "BarBot".into()],
+                    rules: vec![
+                        Rule {
+                            kind: RuleKind::Disallow,
+                            pattern: "/private".into(),
+                            line: 3,
+                        },
+                        Rule {
+                            kind: RuleKind::Allow,
+                            pattern: "/private/public".into(),
+                            line: 4,
+                        },
+                    ],
+                    start_line: 1,
+                    end_line: 5,
+                },
+                Group {
+                    user_agents: vec!["*".into()],
+                    rules: vec![Rule {
+                        kind: RuleKind::Disallow,
+                        pattern: "/tmp".into(),
+                        line: 8,
+                    }],
+                    start_line: 7,
+                    end_line: 8,
+                },
+            ],
+            robots.groups()
+        );
+        assert_eq!(robots.clone(), robots);
+        assert_ne!(Robots::parse("disallow: /tmp\n"), robots);
+    }
+
+    #[test]
+    fn test_blanket_disallow() {
+        let robots_body = "user-agent: FooBot\n\
+        disallow: /\n\
+        allow:\n\
+        \n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        \n\
+        user-agent: *\n\
+        user-agent: BarBot\n\
+        disallow: /\n\
+        allow: /public\n\
+        \n\
+        user-agent: BazBot\n\
+        disallow: /private\n";
+        let robots = Robots::parse(robots_body);
+        assert!(robots.blanket_disallowed("FooBot"));
+        assert!(robots.blanket_disallowed("foobot"));
+        // BarBot's second group has an allow rule.
+        assert!(!robots.blanket_disallowed("BarBot"));
+        assert!(!robots.blanket_disallowed("BazBot"));
+        assert!(!robots.blanket_disallowed("QuxBot"));
+
+        let mut matcher = DefaultMatcher::default();
+        for user_agent in &["FooBot", "BarBot", "BazBot", "QuxBot"] {
+            for url in &[
+                "http://foo.com/",
+                "http://foo.com/public",
+                "http://foo.com/private",
+            ] {
+                assert_eq!(
+                    matcher.one_agent_allowed_by_robots(robots_body, user_agent, url),
+                    robots.allowed(user_agent, url),
+                    "{} {}",
+                    user_agent,
+                    url
+                );
+            }
+        }
+
+        let robots = Robots::parse("user-agent: *\ndisallow: /\nuser-agent: FooBot\nallow: /a\n");
+        assert!(robots.blanket_disallowed("BarBot"));
+        assert!(!robots.blanket_disallowed("FooBot"));
+        assert!(!robots.allowed("BarBot", "http://foo.com/a"));
+    }
+}